@@ -39,6 +39,7 @@ const QualityCheck = ({ font }: QualityCheckProps) => {
       <CommonWrappedGroup font={font} label="Less than" left="<" />
       <CommonWrappedGroup font={font} label="Greater than" left=">" />
       <CommonWrappedGroup font={font} label="Equal to" left="=" />
+      {font.colored.length > 0 && <Wrapped label="Colour" strings={font.colored} left="" right="" columns={13} />}
       <Wrapped label="Everything" strings={font.nonWhitespace} left="" right="" columns={13} />
     </div>
   );
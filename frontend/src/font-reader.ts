@@ -5,29 +5,63 @@ export default class FontReader {
 
   private readonly font: Font;
   private readonly _characters: string[];
+  private readonly _colored: string[];
 
-  constructor(font: Font) {
+  constructor(font: Font, colorGlyphs: Set<number> = new Set()) {
     this.font = font;
 
     const characters: string[] = [];
+    const colored: string[] = [];
     for (let i = 0; i < this.font.glyphs.length; i++) {
       const glyph = this.font.glyphs.get(i);
       if (glyph.unicode) {
-        characters.push(String.fromCharCode(glyph.unicode));
+        characters.push(String.fromCodePoint(glyph.unicode));
+        if (colorGlyphs.has(i)) {
+          colored.push(String.fromCodePoint(glyph.unicode));
+        }
       }
     }
     this._characters = characters;
+    this._colored = colored;
   }
 
   static fromBuffer(arrayBuffer: ArrayBuffer): FontReader {
     const font = parse(arrayBuffer);
-    return new FontReader(font);
+    return new FontReader(font, FontReader.colorGlyphs(arrayBuffer));
+  }
+
+  // glyph ids with layers in the COLR table, which opentype.js doesn't read
+  private static colorGlyphs(arrayBuffer: ArrayBuffer): Set<number> {
+    const view = new DataView(arrayBuffer);
+    const glyphs = new Set<number>();
+
+    const numTables = view.getUint16(4);
+    for (let i = 0; i < numTables; i++) {
+      const record = 12 + i * 16;
+      const tag = String.fromCharCode(...new Uint8Array(arrayBuffer, record, 4));
+      if (tag !== "COLR") {
+        continue;
+      }
+
+      const table = view.getUint32(record + 8);
+      const numBaseGlyphs = view.getUint16(table + 2);
+      const baseGlyphs = table + view.getUint32(table + 4);
+      for (let j = 0; j < numBaseGlyphs; j++) {
+        glyphs.add(view.getUint16(baseGlyphs + j * 6));
+      }
+    }
+
+    return glyphs;
   }
 
   get characters(): string[] {
     return this._characters;
   }
 
+  get colored(): string[] {
+    return this._colored;
+  }
+
   get numbers(): string[] {
     return this.characters.filter(FontReader.isNumber);
   }
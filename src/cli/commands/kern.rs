@@ -0,0 +1,137 @@
+use std::{
+  collections::HashMap,
+  fs,
+  io::{self, BufWriter, Write},
+  path::PathBuf,
+};
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use crate::{
+  cli::commands::util::current_project,
+  font::kerning::{KerningEntry, KerningOrigin},
+};
+
+#[derive(Args)]
+pub struct KernArgs {
+  /// Output format
+  #[arg(long, value_enum, default_value_t = KernFormat::Print)]
+  format: KernFormat,
+  /// Text file used to rank pairs by how often they occur
+  #[arg(long)]
+  corpus: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum KernFormat {
+  Print,
+  Csv,
+  Json,
+}
+
+#[derive(Serialize)]
+struct KernRow {
+  left: char,
+  right: char,
+  value: i16,
+  origin: KerningOrigin,
+  limit: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  frequency: Option<usize>,
+}
+
+pub fn kern(args: &KernArgs) -> Result<()> {
+  let project = current_project()?;
+  let kerning = project.kerning()?;
+
+  let frequencies = match &args.corpus {
+    Some(path) => Some(frequencies(&fs::read_to_string(path)?)),
+    None => None,
+  };
+
+  let mut rows: Vec<KernRow> = kerning
+    .into_iter()
+    .map(|((left, right), entry)| row(left, right, entry, frequencies.as_ref()))
+    .collect();
+  rows.sort_by_key(|row| (std::cmp::Reverse(row.frequency), row.left, row.right));
+
+  let mut out = BufWriter::new(io::stdout().lock());
+  match args.format {
+    KernFormat::Print => print(&mut out, &rows)?,
+    KernFormat::Csv => csv(&mut out, &rows)?,
+    KernFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(&rows)?)?,
+  }
+  out.flush()?;
+
+  Ok(())
+}
+
+fn row(
+  left: char,
+  right: char,
+  entry: KerningEntry,
+  frequencies: Option<&HashMap<(char, char), usize>>,
+) -> KernRow {
+  KernRow {
+    left,
+    right,
+    value: entry.value,
+    origin: entry.origin,
+    limit: entry.limited,
+    frequency: frequencies.map(|f| f.get(&(left, right)).copied().unwrap_or(0)),
+  }
+}
+
+// occurrences of every pair of adjacent characters in the corpus
+fn frequencies(corpus: &str) -> HashMap<(char, char), usize> {
+  let chars: Vec<char> = corpus.chars().collect();
+  let mut frequencies = HashMap::new();
+  for pair in chars.windows(2) {
+    *frequencies.entry((pair[0], pair[1])).or_default() += 1;
+  }
+
+  frequencies
+}
+
+fn print(out: &mut impl Write, rows: &[KernRow]) -> io::Result<()> {
+  for row in rows {
+    let limit = if row.limit { "limit" } else { "" };
+    let frequency = row.frequency.map(|f| f.to_string()).unwrap_or_default();
+    let line = format!(
+      "{}{}  {:>4}  {:<6}  {:<5}  {}",
+      row.left, row.right, row.value, row.origin, limit, frequency
+    );
+    writeln!(out, "{}", line.trim_end())?;
+  }
+
+  Ok(())
+}
+
+fn csv(out: &mut impl Write, rows: &[KernRow]) -> io::Result<()> {
+  writeln!(out, "left,right,value,origin,limit,frequency")?;
+  for row in rows {
+    let frequency = row.frequency.map(|f| f.to_string()).unwrap_or_default();
+    writeln!(
+      out,
+      "{},{},{},{},{},{}",
+      csv_field(row.left),
+      csv_field(row.right),
+      row.value,
+      row.origin,
+      row.limit,
+      frequency
+    )?;
+  }
+
+  Ok(())
+}
+
+fn csv_field(c: char) -> String {
+  match c {
+    '"' => "\"\"\"\"".to_string(),
+    ',' | '\n' | '\r' => format!("\"{c}\""),
+    c => c.to_string(),
+  }
+}
@@ -1,8 +1,12 @@
 mod build;
 mod init;
+mod kern;
 mod server;
+mod sheet;
 mod util;
 
 pub use build::build;
 pub use init::init;
+pub use kern::{KernArgs, kern};
 pub use server::server;
+pub use sheet::{SheetArgs, sheet};
@@ -0,0 +1,47 @@
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+
+use crate::{cli::commands::util::current_project, font::config::Config};
+
+#[derive(Args)]
+pub struct SheetArgs {
+  #[command(subcommand)]
+  command: SheetCommand,
+}
+
+#[derive(Subcommand)]
+enum SheetCommand {
+  /// Move every drawn tile to where config.json now puts it
+  Migrate {
+    /// The config.json the sheet was drawn with
+    old_config: PathBuf,
+  },
+}
+
+pub fn sheet(args: &SheetArgs) -> Result<()> {
+  match &args.command {
+    SheetCommand::Migrate { old_config } => migrate(old_config),
+  }
+}
+
+fn migrate(old_config: &Path) -> Result<()> {
+  let project = current_project()?;
+
+  let old_config =
+    fs::read(old_config).with_context(|| format!("Could not read {}", old_config.display()))?;
+  let old_config: Config =
+    serde_json::from_slice(&old_config).context("Could not deserialize the old config")?;
+
+  let old_sheet = project.migrate_sheet(&old_config)?;
+  println!(
+    "Migrated the sheet, the old one is kept as {}",
+    old_sheet.display()
+  );
+
+  Ok(())
+}
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
-use crate::cli::commands::{build, init, server};
+use crate::cli::commands::{KernArgs, SheetArgs, build, init, kern, server, sheet};
 
 #[derive(Parser)]
 #[command(name = "foundry")]
@@ -14,7 +14,9 @@ pub struct Handler {
 enum Commands {
   Init,
   Build,
+  Kern(KernArgs),
   Server,
+  Sheet(SheetArgs),
 }
 
 impl Handler {
@@ -24,7 +26,9 @@ impl Handler {
     match handler.command {
       Commands::Init => init(),
       Commands::Build => build(),
+      Commands::Kern(args) => kern(&args),
       Commands::Server => server(),
+      Commands::Sheet(args) => sheet(&args),
     }
   }
 }
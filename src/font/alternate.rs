@@ -0,0 +1,54 @@
+use anyhow::{Result, bail};
+use read_fonts::types::Tag;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct Alternate {
+  character: char,
+  feature: String,
+}
+
+impl Alternate {
+  pub fn character(&self) -> char {
+    self.character
+  }
+
+  pub fn feature(&self) -> &str {
+    &self.feature
+  }
+
+  pub fn tag(&self) -> Result<Tag> {
+    if !is_alternate_feature(&self.feature) {
+      bail!(
+        "Unsupported feature \"{}\" for alternate of '{}'",
+        self.feature,
+        self.character
+      );
+    }
+
+    Ok(Tag::new_checked(self.feature.as_bytes())?)
+  }
+}
+
+pub fn is_alternate_feature(feature: &str) -> bool {
+  match feature.as_bytes() {
+    b"salt" | b"zero" | b"smcp" | b"c2sc" | b"case" => true,
+    [b's', b's', tens, ones] => feature_index(*tens, *ones).is_some_and(|i| i <= 20),
+    [b'c', b'v', tens, ones] => feature_index(*tens, *ones).is_some(),
+    _ => false,
+  }
+}
+
+pub fn is_named_feature(feature: &str) -> bool {
+  (feature.starts_with("ss") || feature.starts_with("cv")) && is_alternate_feature(feature)
+}
+
+// the two digit suffix of ssXX and cvXX, 01 through 99
+fn feature_index(tens: u8, ones: u8) -> Option<u8> {
+  if !tens.is_ascii_digit() || !ones.is_ascii_digit() {
+    return None;
+  }
+
+  let index = (tens - b'0') * 10 + (ones - b'0');
+  (index != 0).then_some(index)
+}
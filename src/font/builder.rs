@@ -1,10 +1,10 @@
 use std::{
   cmp::{max, min},
-  collections::HashMap,
+  collections::{BTreeMap, BTreeSet, HashMap},
   vec,
 };
 
-use anyhow::Result;
+use anyhow::{Result, ensure};
 use multimap::MultiMap;
 use read_fonts::{
   tables::{cmap::PlatformId, layout::LookupFlag, os2::SelectionFlags},
@@ -14,37 +14,81 @@ use time::UtcDateTime;
 use write_fonts::{
   FontBuilder, OffsetMarker,
   tables::{
-    cmap::{Cmap, Cmap4, CmapSubtable, EncodingRecord},
+    cmap::{Cmap, Cmap4, Cmap12, CmapSubtable, EncodingRecord, SequentialMapGroup},
+    colr::{self, BaseGlyph, Colr},
+    cpal::{ColorRecord, Cpal},
+    gdef::Gdef,
     glyf::{Bbox, Glyf, GlyfLocaBuilder, SimpleGlyph},
     gpos::{
-      Gpos, PairPos, PairPosFormat1, PairSet, PairValueRecord, PositionLookup, PositionLookupList,
+      AnchorTable, BaseArray, BaseRecord, Class1Record, Class2Record, Gpos, Mark2Array,
+      Mark2Record, MarkArray, MarkBasePosFormat1, MarkMarkPosFormat1, MarkRecord, PairPos,
+      PairPosFormat1, PairPosFormat2, PairSet, PairValueRecord, PositionLookup, PositionLookupList,
       ValueRecord,
     },
-    gsub::{Gsub, SubstitutionLookupList},
+    gsub::{
+      AlternateSet, AlternateSubstFormat1, Gsub, Ligature, LigatureSet, LigatureSubstFormat1,
+      SingleSubst, SingleSubstFormat2, SubstitutionLookup, SubstitutionLookupList,
+    },
     head::{Flags, Head, MacStyle},
     hhea::Hhea,
     hmtx::Hmtx,
     layout::{
-      CoverageFormat1, CoverageTable, Feature, FeatureList, FeatureRecord, LangSys, Lookup, Script,
-      ScriptList, ScriptRecord,
+      CharacterVariantParams, ClassDef, CoverageFormat1, CoverageTable, Feature, FeatureList,
+      FeatureParams, FeatureRecord, LangSys, Lookup, Script, ScriptList, ScriptRecord,
+      StylisticSetParams,
     },
     loca::{Loca, LocaFormat},
     maxp::Maxp,
     name::{Name, NameRecord},
     os2::Os2,
     post::Post,
-    vmtx::LongMetric,
+    vhea::Vhea,
+    vmtx::{LongMetric, Vmtx},
   },
   types::{BoundingBox, FWord, Fixed, LongDateTime, NameId, Version16Dot16},
 };
 
 use crate::font::{
-  config::Config, dimensions::Dimensions, glyphs::Glyph, metadata::Metadata,
-  unicode_char::UnicodeChar,
+  alternate,
+  config::Config,
+  connectivity::Connectivity,
+  contour::{bridge_corners, split_corners},
+  dimensions::Dimensions,
+  glyphs::{Glyph, VERT_TAG},
+  kerning::Kerning,
+  kerning_classes::KerningClasses,
+  ligature::{self, LigatureFeature},
+  metadata::Metadata,
+  point::Point,
+  unicode_char::{MarkClass, UnicodeChar},
 };
 
 const UNITS_PER_EM: u16 = 2048;
 const GRID_SIZE: u16 = 16;
+const MAX_SUBTABLE_SIZE: usize = u16::MAX as usize;
+
+const LATIN_SUPPLEMENT_BIT: usize = 1;
+const LATIN_A_BIT: usize = 2;
+const GREEK_BIT: usize = 7;
+const CYRILLIC_BIT: usize = 9;
+
+// OS/2 unicode range bits and the blocks they stand for
+const UNICODE_BLOCKS: [(usize, char, char); 13] = [
+  (0, '\u{0000}', '\u{007F}'),
+  (LATIN_SUPPLEMENT_BIT, '\u{0080}', '\u{00FF}'),
+  (LATIN_A_BIT, '\u{0100}', '\u{017F}'),
+  (3, '\u{0180}', '\u{024F}'),
+  (GREEK_BIT, '\u{0370}', '\u{03FF}'),
+  (CYRILLIC_BIT, '\u{0400}', '\u{04FF}'),
+  (31, '\u{2000}', '\u{206F}'),
+  (33, '\u{20A0}', '\u{20CF}'),
+  (43, '\u{2500}', '\u{257F}'),
+  (44, '\u{2580}', '\u{259F}'),
+  // everything past the basic multilingual plane
+  (57, '\u{10000}', '\u{10FFFF}'),
+  (60, '\u{E000}', '\u{F8FF}'),
+  (82, '\u{2800}', '\u{28FF}'),
+];
 
 pub struct Builder {
   glyphs: Vec<Glyph>,
@@ -64,10 +108,29 @@ impl Builder {
   pub fn build(&mut self, config: &Config) -> Result<Vec<u8>> {
     let scale_factor: i16 = (UNITS_PER_EM / GRID_SIZE) as i16;
     let one_unit = scale_factor;
+    let color = self.color_tables();
     self
       .glyphs
       .iter_mut()
       .for_each(|glyph| glyph.scale_data(scale_factor));
+    // an eighth of a pixel, enough to keep diagonal strokes solid
+    //   or apart without reaching any pixel center
+    let corner_size = scale_factor / 8;
+    match config.connectivity() {
+      Connectivity::Orthogonal => {}
+      Connectivity::Bridge => self
+        .glyphs
+        .iter_mut()
+        .for_each(|glyph| bridge_corners(&mut glyph.contours, corner_size)),
+      Connectivity::Split => self
+        .glyphs
+        .iter_mut()
+        .for_each(|glyph| split_corners(&mut glyph.contours, corner_size)),
+    }
+    if config.connectivity() != Connectivity::Orthogonal {
+      let bridge = config.connectivity() == Connectivity::Bridge;
+      self.verify_corners(scale_factor, corner_size, bridge)?;
+    }
     self
       .kerning
       .iter_mut()
@@ -89,13 +152,23 @@ impl Builder {
 
     let (cmap, first_code, last_code) = self.cmap();
 
-    let os2 = self.os2(config.metadata(), first_code, last_code, &hhea);
+    let os2 = self.os2(
+      config.metadata(),
+      config.ligatures(),
+      first_code,
+      last_code,
+      &hhea,
+    );
+
+    let feature_names = self.feature_names(config.feature_names());
 
-    let gsub = Builder::gsub();
+    let gsub = self.gsub(config.ligatures(), &feature_names);
 
-    let gpos = self.gpos();
+    let gpos = self.gpos(config.kerning());
 
-    let name = Builder::name(config.metadata());
+    let gdef = self.gdef();
+
+    let name = Builder::name(config.metadata(), config.feature_names(), &feature_names);
     let post = Builder::post();
 
     builder.add_table(&head)?;
@@ -108,13 +181,139 @@ impl Builder {
     builder.add_table(&glyf)?;
     builder.add_table(&gsub)?;
     builder.add_table(&gpos)?;
+    if let Some(gdef) = &gdef {
+      builder.add_table(gdef)?;
+    }
+    if let Some((colr, cpal)) = &color {
+      builder.add_table(colr)?;
+      builder.add_table(cpal)?;
+    }
+    if config.kerning().legacy_table() {
+      builder.add_raw(Tag::new(b"kern"), self.kern());
+    }
     builder.add_table(&name)?;
     builder.add_table(&post)?;
 
+    if config.vertical() {
+      let vmtx = self.vmtx(config.dimensions(), one_unit);
+      let vhea = Builder::vhea(config.dimensions(), &vmtx, &self.glyphs, one_unit);
+      builder.add_table(&vhea)?;
+      builder.add_table(&vmtx)?;
+    }
+
     let bytes = builder.build();
     Ok(bytes)
   }
 
+  // joined or split corners must still fill exactly the traced pixels,
+  //   and every corner diagonal neighbours share must have been resolved
+  fn verify_corners(&self, scale: i16, size: i16, bridge: bool) -> Result<()> {
+    let mismatches: Vec<String> = self
+      .glyphs
+      .iter()
+      .filter_map(|glyph| {
+        if let Some(diff) = glyph.outline_diff(scale) {
+          return Some(format!("'{}' (+ added, - lost):\n{diff}", glyph.character));
+        }
+
+        let faults = glyph.corner_faults(scale, size, bridge);
+        (!faults.is_empty()).then(|| {
+          let corners: Vec<String> = faults
+            .iter()
+            .map(|p| format!("({}, {})", p.x, p.y))
+            .collect();
+          format!(
+            "'{}' at the corners {}\n",
+            glyph.character,
+            corners.join(", ")
+          )
+        })
+      })
+      .collect();
+
+    ensure!(
+      mismatches.is_empty(),
+      "Outlines of {} glyphs no longer match their pixels after resolving diagonal corners:\n\n{}",
+      mismatches.len(),
+      mismatches.join("\n")
+    );
+
+    Ok(())
+  }
+
+  // moves the layers of colour glyphs into glyphs of their own,
+  //   leaving every base glyph as its single colour fallback
+  fn color_tables(&mut self) -> Option<(Colr, Cpal)> {
+    const FOREGROUND: u16 = 0xFFFF;
+
+    if self.glyphs.iter().all(|g| g.layers.is_empty()) {
+      return None;
+    }
+
+    let palette: Vec<[u8; 4]> = self
+      .glyphs
+      .iter()
+      .flat_map(|g| &g.layers)
+      .filter(|layer| !layer.is_foreground())
+      .map(|layer| layer.color)
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect();
+
+    let first_layer = self.glyphs.len();
+    let mut base_glyphs = Vec::new();
+    let mut layers = Vec::new();
+    let mut layer_glyphs = Vec::new();
+    for (gid, glyph) in self.glyphs.iter_mut().enumerate() {
+      if glyph.layers.is_empty() {
+        continue;
+      }
+
+      base_glyphs.push(BaseGlyph::new(
+        GlyphId16::new(gid as u16),
+        layers.len() as u16,
+        glyph.layers.len() as u16,
+      ));
+      for layer in std::mem::take(&mut glyph.layers) {
+        let palette_index = match palette.binary_search(&layer.color) {
+          Ok(index) => index as u16,
+          Err(_) => FOREGROUND,
+        };
+        let layer_gid = first_layer + layer_glyphs.len();
+        layers.push(colr::Layer::new(
+          GlyphId16::new(layer_gid as u16),
+          palette_index,
+        ));
+        layer_glyphs.push(Glyph::new(
+          UnicodeChar::Layer(layer_glyphs.len() as u16),
+          layer.contours,
+          layer.pixels,
+        ));
+      }
+    }
+    self.glyphs.extend(layer_glyphs);
+
+    let num_layers = layers.len() as u16;
+    let colr = Colr::new(
+      base_glyphs.len() as u16,
+      Some(base_glyphs),
+      Some(layers),
+      num_layers,
+    );
+    let color_records: Vec<ColorRecord> = palette
+      .iter()
+      .map(|[r, g, b, a]| ColorRecord::new(*b, *g, *r, *a))
+      .collect();
+    let cpal = Cpal::new(
+      palette.len() as u16,
+      1,
+      palette.len() as u16,
+      Some(color_records),
+      vec![0],
+    );
+    Some((colr, cpal))
+  }
+
   fn head(bounding_box: BoundingBox<i16>, loca_format: LocaFormat) -> Head {
     const FONT_REVISION: Fixed = Fixed::from_i32(1);
     const CHECKSUM_ADJUSTMENT: u32 = 0;
@@ -186,6 +385,60 @@ impl Builder {
     )
   }
 
+  fn vhea(dimensions: &Dimensions, vmtx: &Vmtx, glyphs: &[Glyph], one_unit: i16) -> Vhea {
+    const LINE_GAP: FWord = FWord::new(0);
+
+    const CARET_SLOPE_RISE: i16 = 0;
+    const CARET_SLOPE_RUN: i16 = 1;
+    const CARET_OFFSET: i16 = 0;
+
+    let column_width = dimensions.tile_height() as i16 * one_unit;
+    let ascender = column_width / 2;
+    let descender = ascender - column_width;
+
+    let advance_height_max = vmtx
+      .v_metrics
+      .iter()
+      .map(|metric| metric.advance)
+      .max()
+      .unwrap();
+    let min_top_side_bearing = vmtx
+      .v_metrics
+      .iter()
+      .map(|metric| metric.side_bearing)
+      .min()
+      .unwrap();
+
+    let extents = vmtx
+      .v_metrics
+      .iter()
+      .zip(glyphs)
+      .map(|(metric, glyph)| metric.side_bearing + glyph.bbox.y_max - glyph.bbox.y_min);
+    let min_bottom_side_bearing = vmtx
+      .v_metrics
+      .iter()
+      .zip(extents.clone())
+      .map(|(metric, extent)| metric.advance as i16 - extent)
+      .min()
+      .unwrap();
+    let y_max_extent = extents.max().unwrap();
+    let number_of_long_ver_metrics: u16 = vmtx.v_metrics.len() as u16;
+
+    Vhea::new(
+      ascender.into(),
+      descender.into(),
+      LINE_GAP,
+      advance_height_max.into(),
+      min_top_side_bearing.into(),
+      min_bottom_side_bearing.into(),
+      y_max_extent.into(),
+      CARET_SLOPE_RISE,
+      CARET_SLOPE_RUN,
+      CARET_OFFSET,
+      number_of_long_ver_metrics,
+    )
+  }
+
   fn maxp(&self) -> Maxp {
     const NON_COMPOSITE: u16 = 0;
     const MAX_ZONES: u16 = 2;
@@ -223,13 +476,17 @@ impl Builder {
   }
 
   #[allow(clippy::too_many_lines)]
-  fn os2(&self, metadata: &Metadata, first_code: u16, last_code: u16, hhea: &Hhea) -> Os2 {
+  fn os2(
+    &self,
+    metadata: &Metadata,
+    ligatures: &[ligature::Ligature],
+    first_code: u16,
+    last_code: u16,
+    hhea: &Hhea,
+  ) -> Os2 {
     const MEDIUM_WIDTH_CLASS: u16 = 5;
     const FS_TYPE_INSTALLABLE_EMBEDDING: u16 = 0;
     const S_FAMILY_CLASS_NO_CLASSIFICATION: i16 = 0;
-    const UNICODE_RANGE_1: u32 = 0b0111;
-    const UNICODE_RANGE_2: u32 = 0b0010;
-    const UNICODE_RANGE_3_AND_4: u32 = 0;
     const ACH_VEND_ID: Tag = Tag::new(b"FDRY");
     const SELECTION_FLAGS: SelectionFlags = SelectionFlags::REGULAR;
     const S_TYPO_LINE_GAP: i16 = 0;
@@ -244,15 +501,13 @@ impl Builder {
     const PANOSE_NO_MIDLINE: u8 = 0;
     const PANOSE_NO_X_HEIGHT: u8 = 0;
 
-    const PAGE_1: u32 = {
-      const WINDOWS_1252_LATIN_1: u32 = 1 << 0;
-      const WINDOWS_1250_LATIN_2: u32 = 1 << 1;
-      const WINDOWS_1254_TURKISH: u32 = 1 << 4;
-      const WINDOWS_1257_BALTIC: u32 = 1 << 7;
-      WINDOWS_1252_LATIN_1 | WINDOWS_1250_LATIN_2 | WINDOWS_1254_TURKISH | WINDOWS_1257_BALTIC
-    };
+    const WINDOWS_1252_LATIN_1: u32 = 1 << 0;
+    const WINDOWS_1250_LATIN_2: u32 = 1 << 1;
+    const WINDOWS_1251_CYRILLIC: u32 = 1 << 2;
+    const WINDOWS_1253_GREEK: u32 = 1 << 3;
+    const WINDOWS_1254_TURKISH: u32 = 1 << 4;
+    const WINDOWS_1257_BALTIC: u32 = 1 << 7;
     const PAGE_2_UNSUPPORTED: u32 = 0;
-    const UL_CODE_PAGE_RANGE: (u32, u32) = (PAGE_1, PAGE_2_UNSUPPORTED);
 
     const LOW_X_CHAR: UnicodeChar = UnicodeChar::Char('x');
 
@@ -263,7 +518,6 @@ impl Builder {
     const US_BREAK_CHAR: Option<u16> = Some(SPACE_UNICODE);
 
     const PAIR_KERNING: u16 = 2;
-    const US_MAX_CONTEXT: Option<u16> = Some(PAIR_KERNING);
 
     const NOT_OPTICAL: Option<u16> = None;
 
@@ -277,15 +531,38 @@ impl Builder {
       .sum::<i32>()
       / num_glyphs) as i16;
 
+    // zero when the charset has no 'x'
     let sx_height = self
       .glyphs
       .iter()
       .find(|g| g.character == LOW_X_CHAR)
-      .map(|g| g.bbox.y_max);
+      .map_or(0, |g| g.bbox.y_max);
+
+    let unicode_ranges = self.unicode_ranges();
+    let has_block = |bit: usize| unicode_ranges[bit / 32] & (1 << (bit % 32)) != 0;
+    let mut code_pages = 0;
+    if has_block(LATIN_SUPPLEMENT_BIT) {
+      code_pages |= WINDOWS_1252_LATIN_1;
+    }
+    if has_block(LATIN_A_BIT) {
+      code_pages |= WINDOWS_1250_LATIN_2 | WINDOWS_1254_TURKISH | WINDOWS_1257_BALTIC;
+    }
+    if has_block(CYRILLIC_BIT) {
+      code_pages |= WINDOWS_1251_CYRILLIC;
+    }
+    if has_block(GREEK_BIT) {
+      code_pages |= WINDOWS_1253_GREEK;
+    }
 
     let ascender: i16 = hhea.ascender.into();
     let descender: i16 = hhea.descender.into();
 
+    let longest_ligature = ligatures
+      .iter()
+      .map(|ligature| ligature.sequence().chars().count() as u16)
+      .max()
+      .unwrap_or_default();
+
     Os2 {
       x_avg_char_width,
       us_weight_class: metadata.font_subfamily().weight_class(),
@@ -314,10 +591,10 @@ impl Builder {
         PANOSE_NO_MIDLINE,
         PANOSE_NO_X_HEIGHT,
       ],
-      ul_unicode_range_1: UNICODE_RANGE_1,
-      ul_unicode_range_2: UNICODE_RANGE_2,
-      ul_unicode_range_3: UNICODE_RANGE_3_AND_4,
-      ul_unicode_range_4: UNICODE_RANGE_3_AND_4,
+      ul_unicode_range_1: unicode_ranges[0],
+      ul_unicode_range_2: unicode_ranges[1],
+      ul_unicode_range_3: unicode_ranges[2],
+      ul_unicode_range_4: unicode_ranges[3],
       ach_vend_id: ACH_VEND_ID,
       fs_selection: SELECTION_FLAGS,
       us_first_char_index: first_code,
@@ -327,28 +604,47 @@ impl Builder {
       s_typo_line_gap: S_TYPO_LINE_GAP,
       us_win_ascent: ascender.unsigned_abs(),
       us_win_descent: descender.unsigned_abs(),
-      ul_code_page_range_1: Some(UL_CODE_PAGE_RANGE.0),
-      ul_code_page_range_2: Some(UL_CODE_PAGE_RANGE.1),
-      sx_height,
+      ul_code_page_range_1: Some(code_pages),
+      ul_code_page_range_2: Some(PAGE_2_UNSUPPORTED),
+      sx_height: Some(sx_height),
       s_cap_height: Some(ascender),
       us_default_char: US_DEFAULT_CHAR,
       us_break_char: US_BREAK_CHAR,
-      us_max_context: US_MAX_CONTEXT,
+      us_max_context: Some(max(PAIR_KERNING, longest_ligature)),
       us_lower_optical_point_size: NOT_OPTICAL,
       us_upper_optical_point_size: NOT_OPTICAL,
     }
   }
 
+  // the OS/2 bit of every unicode block with a glyph drawn in it
+  fn unicode_ranges(&self) -> [u32; 4] {
+    let mut ranges = [0; 4];
+    for (bit, first, last) in UNICODE_BLOCKS {
+      let covered = self.glyphs.iter().any(|g| match g.character {
+        UnicodeChar::Char(c) => !g.character.is_space() && (first..=last).contains(&c),
+        _ => false,
+      });
+      if covered {
+        ranges[bit / 32] |= 1 << (bit % 32);
+      }
+    }
+    ranges
+  }
+
   fn hmtx(&self, dimensions: &Dimensions, one_unit: i16) -> Hmtx {
     let h_metrics = self
       .glyphs
       .iter()
       .map(|glyph| {
-        let advance: u16 = if glyph.character.is_space() {
-          let space_width = dimensions.space_width();
-          space_width * (one_unit as u16)
-        } else {
-          (glyph.bbox.x_max + one_unit) as u16
+        let advance: u16 = match glyph.character {
+          c if c.is_space() => {
+            let space_width = dimensions.space_width();
+            space_width * (one_unit as u16)
+          }
+          UnicodeChar::Alternate(_, VERT_TAG, _) => dimensions.tile_height() * (one_unit as u16),
+          c if c.fills_tile() => dimensions.tile_width() * (one_unit as u16),
+          c if c.mark_class().is_some() => 0,
+          _ => (glyph.bbox.x_max + one_unit) as u16,
         };
 
         LongMetric::new(advance, glyph.bbox.x_min)
@@ -357,10 +653,25 @@ impl Builder {
     Hmtx::new(h_metrics, Vec::new())
   }
 
+  fn vmtx(&self, dimensions: &Dimensions, one_unit: i16) -> Vmtx {
+    let advance = dimensions.tile_height() * (one_unit as u16);
+    let top = dimensions.ascender_height() as i16 * one_unit;
+
+    let v_metrics = self
+      .glyphs
+      .iter()
+      .map(|glyph| LongMetric::new(advance, top - glyph.bbox.y_max))
+      .collect();
+    Vmtx::new(v_metrics, Vec::new())
+  }
+
   fn cmap(&self) -> (Cmap, u16, u16) {
     const UNICODE_ENCODING_ID: u16 = 3;
+    const UNICODE_FULL_ENCODING_ID: u16 = 4;
     const WINDOWS_ENCODING_ID: u16 = 1;
+    const WINDOWS_FULL_ENCODING_ID: u16 = 10;
     const CMAP_4_LANGUAGE: u16 = 0;
+    const CMAP_12_LANGUAGE: u32 = 0;
     const GLYPH_ID_ARRAY: Vec<u16> = Vec::new();
 
     let (start_code, end_code, id_delta, id_range_offsets) = self.cmap_segments();
@@ -377,10 +688,21 @@ impl Builder {
     );
     let subtable = CmapSubtable::Format4(cmap4);
 
-    let encoding_records = vec![
-      EncodingRecord::new(PlatformId::Unicode, UNICODE_ENCODING_ID, subtable.clone()),
-      EncodingRecord::new(PlatformId::Windows, WINDOWS_ENCODING_ID, subtable),
-    ];
+    // format 4 stops at U+FFFF, format 12 maps every plane
+    let encoding_records = if let Some(groups) = self.cmap_groups() {
+      let full = CmapSubtable::Format12(Cmap12::new(CMAP_12_LANGUAGE, groups));
+      vec![
+        EncodingRecord::new(PlatformId::Unicode, UNICODE_ENCODING_ID, subtable.clone()),
+        EncodingRecord::new(PlatformId::Unicode, UNICODE_FULL_ENCODING_ID, full.clone()),
+        EncodingRecord::new(PlatformId::Windows, WINDOWS_ENCODING_ID, subtable),
+        EncodingRecord::new(PlatformId::Windows, WINDOWS_FULL_ENCODING_ID, full),
+      ]
+    } else {
+      vec![
+        EncodingRecord::new(PlatformId::Unicode, UNICODE_ENCODING_ID, subtable.clone()),
+        EncodingRecord::new(PlatformId::Windows, WINDOWS_ENCODING_ID, subtable),
+      ]
+    };
     let cmap = Cmap::new(encoding_records);
     (cmap, first_code, last_code)
   }
@@ -396,98 +718,621 @@ impl Builder {
     Ok(builder.build())
   }
 
-  fn gsub() -> Gsub {
+  fn gsub(&self, ligatures: &[ligature::Ligature], feature_names: &HashMap<Tag, NameId>) -> Gsub {
     const DFLT_TAG: Tag = Tag::new(b"DFLT");
     const LATN_TAG: Tag = Tag::new(b"latn");
 
-    let lang_sys = LangSys::new(vec![0]);
+    let glyph_ids: HashMap<UnicodeChar, u16> = self
+      .glyphs
+      .iter()
+      .enumerate()
+      .map(|(id, g)| (g.character, id as u16))
+      .collect();
+
+    let alternate_tags: BTreeSet<Tag> = self
+      .glyphs
+      .iter()
+      .filter_map(|g| match g.character {
+        UnicodeChar::Alternate(_, tag, _) => Some(tag),
+        _ => None,
+      })
+      .collect();
+
+    let mut lookups: Vec<(Tag, SubstitutionLookup)> = Vec::new();
+    for tag in alternate_tags {
+      if let Some(lookup) = self.alternate_subst(tag, &glyph_ids) {
+        lookups.push((tag, lookup));
+      }
+    }
+    for feature in [LigatureFeature::Liga, LigatureFeature::Dlig] {
+      if let Some(lookup) = self.ligature_subst(feature, ligatures, &glyph_ids) {
+        lookups.push((feature.tag(), lookup));
+      }
+    }
+    lookups.sort_by_key(|(tag, _)| *tag);
+
+    let feature_indices: Vec<u16> = (0..lookups.len() as u16).collect();
+    let lang_sys = LangSys::new(feature_indices);
     let script = Script::new(Some(lang_sys), Vec::new());
     let dflt_record = ScriptRecord::new(DFLT_TAG, script.clone());
     let latn_record = ScriptRecord::new(LATN_TAG, script);
 
     let script_list = ScriptList::new(vec![dflt_record, latn_record]);
 
-    let feature_list = FeatureList::new(vec![]);
-    let lookup_list = SubstitutionLookupList::new(vec![]);
+    let (feature_records, lookups): (Vec<FeatureRecord>, Vec<SubstitutionLookup>) = lookups
+      .into_iter()
+      .enumerate()
+      .map(|(i, (tag, lookup))| {
+        let params = feature_names
+          .get(&tag)
+          .map(|name_id| Builder::feature_params(tag, *name_id));
+        let feature = Feature::new(params, vec![i as u16]);
+        (FeatureRecord::new(tag, feature), lookup)
+      })
+      .unzip();
+    let feature_list = FeatureList::new(feature_records);
+    let lookup_list = SubstitutionLookupList::new(lookups);
 
     Gsub::new(script_list, feature_list, lookup_list)
   }
 
-  fn gpos(&self) -> Gpos {
+  fn feature_params(tag: Tag, name_id: NameId) -> FeatureParams {
+    const NO_NAME: NameId = NameId::new(0);
+    const NO_NAMED_PARAMETERS: u16 = 0;
+
+    if tag.to_be_bytes().starts_with(b"cv") {
+      let params = CharacterVariantParams::new(
+        name_id,
+        NO_NAME,
+        NO_NAME,
+        NO_NAMED_PARAMETERS,
+        NO_NAME,
+        Vec::new(),
+      );
+      FeatureParams::CharacterVariant(params)
+    } else {
+      FeatureParams::StylisticSet(StylisticSetParams::new(name_id))
+    }
+  }
+
+  fn alternate_subst(
+    &self,
+    tag: Tag,
+    glyph_ids: &HashMap<UnicodeChar, u16>,
+  ) -> Option<SubstitutionLookup> {
+    let grouped: MultiMap<u16, u16> = self
+      .glyphs
+      .iter()
+      .filter_map(|g| match g.character {
+        UnicodeChar::Alternate(c, t, _) if t == tag => Some((
+          *glyph_ids.get(&UnicodeChar::Char(c))?,
+          *glyph_ids.get(&g.character)?,
+        )),
+        _ => None,
+      })
+      .collect();
+    if grouped.is_empty() {
+      return None;
+    }
+
+    let base_glyphs = {
+      let mut keys: Vec<u16> = grouped.keys().copied().collect();
+      keys.sort_unstable();
+      keys
+    };
+
+    let glyph_array: Vec<GlyphId16> = base_glyphs.iter().copied().map(GlyphId16::new).collect();
+    let coverage = CoverageFormat1::new(glyph_array);
+    let coverage = CoverageTable::Format1(coverage);
+
+    // a single alternate per glyph is a plain substitution,
+    //   several need an alternate set to pick from
+    if grouped
+      .iter_all()
+      .all(|(_, alternates)| alternates.len() == 1)
+    {
+      let substitutes = base_glyphs
+        .iter()
+        .map(|base| GlyphId16::new(*grouped.get(base).unwrap()))
+        .collect();
+      let subst = SingleSubstFormat2::new(coverage, substitutes);
+      let subst = SingleSubst::Format2(subst);
+      let lookup = Lookup::new(LookupFlag::empty(), vec![subst]);
+      Some(SubstitutionLookup::Single(lookup))
+    } else {
+      let alternate_sets = base_glyphs
+        .iter()
+        .map(|base| {
+          let mut alternates = grouped.get_vec(base).cloned().unwrap();
+          alternates.sort_unstable();
+          AlternateSet::new(alternates.into_iter().map(GlyphId16::new).collect())
+        })
+        .collect();
+      let subst = AlternateSubstFormat1::new(coverage, alternate_sets);
+      let lookup = Lookup::new(LookupFlag::empty(), vec![subst]);
+      Some(SubstitutionLookup::Alternate(lookup))
+    }
+  }
+
+  fn ligature_subst(
+    &self,
+    feature: LigatureFeature,
+    ligatures: &[ligature::Ligature],
+    glyph_ids: &HashMap<UnicodeChar, u16>,
+  ) -> Option<SubstitutionLookup> {
+    let mut grouped: MultiMap<u16, (Vec<u16>, u16)> = self
+      .glyphs
+      .iter()
+      .filter_map(|g| {
+        let UnicodeChar::Ligature(i) = g.character else {
+          return None;
+        };
+        let ligature = ligatures.get(usize::from(i))?;
+        if ligature.feature() != feature {
+          return None;
+        }
+
+        let components: Vec<u16> = ligature
+          .sequence()
+          .chars()
+          .map(|c| glyph_ids.get(&UnicodeChar::Char(c)).copied())
+          .collect::<Option<_>>()?;
+        let (first, rest) = components.split_first()?;
+        Some((*first, (rest.to_vec(), *glyph_ids.get(&g.character)?)))
+      })
+      .collect();
+    if grouped.is_empty() {
+      return None;
+    }
+
+    let first_glyphs = {
+      let mut keys: Vec<u16> = grouped.keys().copied().collect();
+      keys.sort_unstable();
+      keys
+    };
+
+    let glyph_array: Vec<GlyphId16> = first_glyphs.iter().copied().map(GlyphId16::new).collect();
+    let coverage = CoverageFormat1::new(glyph_array);
+    let coverage = CoverageTable::Format1(coverage);
+
+    let ligature_sets: Vec<LigatureSet> = first_glyphs
+      .iter()
+      .map(|first| {
+        let mut set = grouped.remove(first).unwrap();
+        // longest sequences first so they win over their prefixes
+        set.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        let ligatures: Vec<Ligature> = set
+          .into_iter()
+          .map(|(rest, ligature)| {
+            let components = rest.into_iter().map(GlyphId16::new).collect();
+            Ligature::new(GlyphId16::new(ligature), components)
+          })
+          .collect();
+        LigatureSet::new(ligatures)
+      })
+      .collect();
+
+    let subst = LigatureSubstFormat1::new(coverage, ligature_sets);
+    let lookup = Lookup::new(LookupFlag::empty(), vec![subst]);
+    Some(SubstitutionLookup::Ligature(lookup))
+  }
+
+  fn gpos(&self, kerning: &Kerning) -> Gpos {
     const DFLT_TAG: Tag = Tag::new(b"DFLT");
     const LATN_TAG: Tag = Tag::new(b"latn");
     const KERN_TAG: Tag = Tag::new(b"kern");
+    const MARK_TAG: Tag = Tag::new(b"mark");
+    const MKMK_TAG: Tag = Tag::new(b"mkmk");
+
+    let mut lookups: Vec<(Tag, PositionLookup)> = vec![(KERN_TAG, self.kern_lookup(kerning))];
+    if let Some(lookup) = self.mark_lookup() {
+      lookups.push((MARK_TAG, lookup));
+    }
+    if let Some(lookup) = self.mkmk_lookup() {
+      lookups.push((MKMK_TAG, lookup));
+    }
 
-    let lang_sys = LangSys::new(vec![0]);
+    let feature_indices: Vec<u16> = (0..lookups.len() as u16).collect();
+    let lang_sys = LangSys::new(feature_indices);
     let script = Script::new(Some(lang_sys), Vec::new());
     let dflt_record = ScriptRecord::new(DFLT_TAG, script.clone());
     let latn_record = ScriptRecord::new(LATN_TAG, script);
     let script_list = ScriptList::new(vec![dflt_record, latn_record]);
 
-    let feature = Feature::new(None, vec![0]);
-    let feature_record = FeatureRecord::new(KERN_TAG, feature);
-    let feature_list = FeatureList::new(vec![feature_record]);
+    let (feature_records, lookups): (Vec<FeatureRecord>, Vec<PositionLookup>) = lookups
+      .into_iter()
+      .enumerate()
+      .map(|(i, (tag, lookup))| {
+        let feature = Feature::new(None, vec![i as u16]);
+        (FeatureRecord::new(tag, feature), lookup)
+      })
+      .unzip();
+    let feature_list = FeatureList::new(feature_records);
+    let lookup_list = PositionLookupList::new(lookups);
+
+    Gpos::new(script_list, feature_list, lookup_list)
+  }
+
+  fn kern_lookup(&self, kerning: &Kerning) -> PositionLookup {
+    let char_to_gid = self.char_to_gid();
+    let gid = |c: &char| char_to_gid[c];
+
+    let mut subtables = Vec::new();
+    if kerning.classes() {
+      let classes = KerningClasses::new(&self.kerning, kerning.groups());
+      let exceptions = classes
+        .exceptions()
+        .iter()
+        .map(|((left, right), value)| ((gid(left), gid(right)), *value))
+        .collect();
+      subtables.extend(Builder::glyph_pair_pos(exceptions));
+      subtables.extend(Builder::class_pair_pos(&classes, &char_to_gid));
+    } else {
+      let pairs = self
+        .kerning
+        .iter()
+        .map(|((left, right), value)| ((gid(left), gid(right)), *value))
+        .collect();
+      subtables.extend(Builder::glyph_pair_pos(pairs));
+    }
+
+    let lookup = Lookup::new(LookupFlag::IGNORE_MARKS, subtables);
+    PositionLookup::Pair(lookup)
+  }
 
-    let char_to_gid: HashMap<char, u16> = self
+  // one PairSet per left glyph, split whenever a subtable would
+  //   outgrow its 16-bit offsets
+  fn glyph_pair_pos(pairs: BTreeMap<(u16, u16), i16>) -> Vec<PairPos> {
+    // format, coverage offset, value formats, pair set count, coverage header
+    const HEADER_SIZE: usize = 14;
+    // pair set offset, coverage glyph, pair value count
+    const PAIR_SET_SIZE: usize = 6;
+    const PAIR_VALUE_RECORD_SIZE: usize = 4;
+
+    let mut grouped: BTreeMap<u16, Vec<(u16, i16)>> = BTreeMap::new();
+    for ((left, right), value) in pairs {
+      grouped.entry(left).or_default().push((right, value));
+    }
+
+    let mut chunks: Vec<Vec<u16>> = Vec::new();
+    let mut size = 0;
+    for (left, rights) in &grouped {
+      let set_size = PAIR_SET_SIZE + rights.len() * PAIR_VALUE_RECORD_SIZE;
+      if chunks.is_empty() || size + set_size > MAX_SUBTABLE_SIZE {
+        chunks.push(Vec::new());
+        size = HEADER_SIZE;
+      }
+      size += set_size;
+      chunks.last_mut().unwrap().push(*left);
+    }
+
+    chunks
+      .into_iter()
+      .map(|chunk| {
+        let glyph_array: Vec<GlyphId16> = chunk.iter().copied().map(GlyphId16::new).collect();
+        let coverage = CoverageTable::Format1(CoverageFormat1::new(glyph_array));
+
+        let pair_sets: Vec<PairSet> = chunk
+          .iter()
+          .map(|left| {
+            let records: Vec<PairValueRecord> = grouped[left]
+              .iter()
+              .map(|(right, value)| {
+                PairValueRecord::new(
+                  (*right).into(),
+                  ValueRecord::new().with_x_advance(*value),
+                  ValueRecord::default(),
+                )
+              })
+              .collect();
+            PairSet::new(records)
+          })
+          .collect();
+        PairPos::Format1(PairPosFormat1::new(coverage, pair_sets))
+      })
+      .collect()
+  }
+
+  // the class matrix, split by left classes whenever a subtable
+  //   would outgrow its 16-bit offsets
+  fn class_pair_pos(classes: &KerningClasses, char_to_gid: &HashMap<char, u16>) -> Vec<PairPos> {
+    // format, offsets, value formats, class counts, coverage header
+    const HEADER_SIZE: usize = 20;
+    const CLASS2_RECORD_SIZE: usize = 2;
+    // worst case of a glyph in its own class range
+    const CLASS_DEF_GLYPH_SIZE: usize = 6;
+    // coverage glyph and class def entry
+    const LEFT_GLYPH_SIZE: usize = 2 + CLASS_DEF_GLYPH_SIZE;
+
+    let class_def2: ClassDef = classes
+      .right()
+      .iter()
+      .enumerate()
+      .flat_map(|(i, class)| {
+        class
+          .iter()
+          .map(move |c| (GlyphId16::new(char_to_gid[c]), i as u16 + 1))
+          .collect::<Vec<_>>()
+      })
+      .collect();
+
+    let record_size = (classes.right().len() + 1) * CLASS2_RECORD_SIZE;
+    let right_glyphs: usize = classes.right().iter().map(Vec::len).sum();
+    let base_size = HEADER_SIZE + right_glyphs * CLASS_DEF_GLYPH_SIZE + record_size;
+
+    let mut chunks: Vec<Vec<usize>> = Vec::new();
+    let mut size = 0;
+    for (i, class) in classes.left().iter().enumerate() {
+      let class_size = record_size + class.len() * LEFT_GLYPH_SIZE;
+      if chunks.is_empty() || size + class_size > MAX_SUBTABLE_SIZE {
+        chunks.push(Vec::new());
+        size = base_size;
+      }
+      size += class_size;
+      chunks.last_mut().unwrap().push(i);
+    }
+
+    chunks
+      .iter()
+      .map(|chunk| {
+        let mut glyph_array: Vec<GlyphId16> = chunk
+          .iter()
+          .flat_map(|i| {
+            classes.left()[*i]
+              .iter()
+              .map(|c| GlyphId16::new(char_to_gid[c]))
+          })
+          .collect();
+        glyph_array.sort_unstable();
+        let coverage = CoverageTable::Format1(CoverageFormat1::new(glyph_array));
+
+        let class_def1: ClassDef = chunk
+          .iter()
+          .enumerate()
+          .flat_map(|(k, i)| {
+            classes.left()[*i]
+              .iter()
+              .map(|c| (GlyphId16::new(char_to_gid[c]), k as u16 + 1))
+              .collect::<Vec<_>>()
+          })
+          .collect();
+
+        let class2_record = |value: i16| {
+          Class2Record::new(
+            ValueRecord::new().with_x_advance(value),
+            ValueRecord::default(),
+          )
+        };
+        let class1_records = std::iter::once(None)
+          .chain(chunk.iter().map(Some))
+          .map(|left| {
+            let records = std::iter::once(0)
+              .chain(
+                (0..classes.right().len())
+                  .map(|right| left.map_or(0, |left| classes.value(*left, right))),
+              )
+              .map(class2_record)
+              .collect();
+            Class1Record::new(records)
+          })
+          .collect();
+
+        PairPos::Format2(PairPosFormat2::new(
+          coverage,
+          class_def1,
+          class_def2.clone(),
+          class1_records,
+        ))
+      })
+      .collect()
+  }
+
+  fn char_to_gid(&self) -> HashMap<char, u16> {
+    self
       .glyphs
       .iter()
       .enumerate()
       .filter_map(|(id, g)| match g.character {
-        UnicodeChar::NotDef => None,
         UnicodeChar::Char(c) => Some((c, id as u16)),
+        _ => None,
       })
-      .collect();
+      .collect()
+  }
 
-    let grouped_kerning: MultiMap<u16, (u16, i16)> = self
+  // format 0 kern table for apps that ignore GPOS,
+  //   split into several subtables when the pairs don't fit in one
+  fn kern(&self) -> Vec<u8> {
+    const SUBTABLE_HEADER_SIZE: usize = 14;
+    const PAIR_SIZE: usize = 6;
+    const MAX_PAIRS: usize = (MAX_SUBTABLE_SIZE - SUBTABLE_HEADER_SIZE) / PAIR_SIZE;
+    const HORIZONTAL: u16 = 0x0001;
+
+    let char_to_gid = self.char_to_gid();
+    let pairs: Vec<(u16, u16, i16)> = self
       .kerning
       .iter()
-      .map(|((left, right), value)| {
-        (
-          *char_to_gid.get(left).unwrap(),
-          (*char_to_gid.get(right).unwrap(), *value),
-        )
+      .map(|((left, right), value)| (char_to_gid[left], char_to_gid[right], *value))
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect();
+
+    let subtables: Vec<&[(u16, u16, i16)]> = pairs.chunks(MAX_PAIRS).collect();
+    if subtables.len() > 1 {
+      eprintln!(
+        "Warning: {} kerning pairs split into {} kern subtables, some apps only read the first",
+        pairs.len(),
+        subtables.len()
+      );
+    }
+
+    let mut data = Vec::new();
+    data.extend(0u16.to_be_bytes());
+    data.extend((subtables.len() as u16).to_be_bytes());
+    for subtable in subtables {
+      let n_pairs = subtable.len() as u16;
+      let power = if n_pairs == 0 {
+        0
+      } else {
+        1 << n_pairs.ilog2()
+      };
+      let search_range = power * PAIR_SIZE as u16;
+      let entry_selector = if power == 0 { 0 } else { power.ilog2() as u16 };
+      let range_shift = n_pairs * PAIR_SIZE as u16 - search_range;
+
+      data.extend(0u16.to_be_bytes());
+      data.extend(((SUBTABLE_HEADER_SIZE + subtable.len() * PAIR_SIZE) as u16).to_be_bytes());
+      data.extend(HORIZONTAL.to_be_bytes());
+      data.extend(n_pairs.to_be_bytes());
+      data.extend(search_range.to_be_bytes());
+      data.extend(entry_selector.to_be_bytes());
+      data.extend(range_shift.to_be_bytes());
+      for (left, right, value) in subtable {
+        data.extend(left.to_be_bytes());
+        data.extend(right.to_be_bytes());
+        data.extend(value.to_be_bytes());
+      }
+    }
+
+    data
+  }
+
+  // marks with their class and attachment anchor, in glyph order
+  fn mark_records(&self) -> Vec<(u16, MarkClass, Point)> {
+    self
+      .glyphs
+      .iter()
+      .enumerate()
+      .filter_map(|(id, g)| {
+        let class = g.character.mark_class()?;
+        let (attach, _) = g.mark_anchors()?;
+        Some((id as u16, class, attach))
       })
+      .collect()
+  }
+
+  fn mark_lookup(&self) -> Option<PositionLookup> {
+    let marks = self.mark_records();
+    let bases: Vec<(u16, [Option<Point>; 2])> = self
+      .glyphs
+      .iter()
+      .enumerate()
+      .filter(|(_, g)| g.character.mark_class().is_none())
+      .map(|(id, g)| (id as u16, g.base_anchors()))
+      .filter(|(_, anchors)| anchors.iter().any(Option::is_some))
       .collect();
+    if marks.is_empty() || bases.is_empty() {
+      return None;
+    }
 
-    let left_glyphs = {
-      let mut keys: Vec<u16> = grouped_kerning.keys().copied().collect();
-      keys.sort_unstable();
-      keys
-    };
+    let (mark_coverage, mark_array) = Builder::mark_array(&marks);
 
-    let glyph_array: Vec<GlyphId16> = left_glyphs.iter().copied().map(GlyphId16::new).collect();
-    let coverage = CoverageFormat1::new(glyph_array);
-    let coverage = CoverageTable::Format1(coverage);
+    let base_glyphs = bases.iter().map(|(id, _)| GlyphId16::new(*id)).collect();
+    let base_coverage = CoverageTable::Format1(CoverageFormat1::new(base_glyphs));
+    let base_records = bases
+      .iter()
+      .map(|(_, anchors)| BaseRecord::new(anchors.iter().map(|a| a.map(Builder::anchor)).collect()))
+      .collect();
+    let base_array = BaseArray::new(base_records);
+
+    let mark_base = MarkBasePosFormat1::new(mark_coverage, base_coverage, mark_array, base_array);
+    let lookup = Lookup::new(LookupFlag::empty(), vec![mark_base]);
+    Some(PositionLookup::MarkToBase(lookup))
+  }
 
-    let pair_sets: Vec<PairSet> = left_glyphs
+  fn mkmk_lookup(&self) -> Option<PositionLookup> {
+    let marks = self.mark_records();
+    let stacking: Vec<(u16, MarkClass, Point)> = self
+      .glyphs
       .iter()
-      .map(|left| {
-        let mut pairs = grouped_kerning.get_vec(left).cloned().unwrap();
-        pairs.sort_by_key(|(right, _)| *right);
+      .enumerate()
+      .filter_map(|(id, g)| {
+        let class = g.character.mark_class()?;
+        let (_, stack) = g.mark_anchors()?;
+        Some((id as u16, class, stack?))
+      })
+      .collect();
+    if marks.is_empty() || stacking.is_empty() {
+      return None;
+    }
 
-        let records: Vec<PairValueRecord> = pairs
-          .into_iter()
-          .map(|(right, value)| {
-            PairValueRecord::new(
-              right.into(),
-              ValueRecord::new().with_x_advance(value),
-              ValueRecord::default(),
-            )
-          })
+    let (mark1_coverage, mark1_array) = Builder::mark_array(&marks);
+
+    let mark2_glyphs = stacking
+      .iter()
+      .map(|(id, _, _)| GlyphId16::new(*id))
+      .collect();
+    let mark2_coverage = CoverageTable::Format1(CoverageFormat1::new(mark2_glyphs));
+    let mark2_records = stacking
+      .iter()
+      .map(|(_, class, anchor)| {
+        let anchors = [MarkClass::Top, MarkClass::Bottom]
+          .iter()
+          .map(|c| (c == class).then(|| Builder::anchor(*anchor)))
           .collect();
-        PairSet::new(records)
+        Mark2Record::new(anchors)
       })
       .collect();
-    let pair_pos = PairPosFormat1::new(coverage, pair_sets);
-    let pair_pos = PairPos::Format1(pair_pos);
-    let lookup = Lookup::new(LookupFlag::IGNORE_MARKS, vec![pair_pos]);
-    let lookup = PositionLookup::Pair(lookup);
-    let lookup_list = PositionLookupList::new(vec![lookup]);
+    let mark2_array = Mark2Array::new(mark2_records);
 
-    Gpos::new(script_list, feature_list, lookup_list)
+    let mark_mark =
+      MarkMarkPosFormat1::new(mark1_coverage, mark2_coverage, mark1_array, mark2_array);
+    let lookup = Lookup::new(LookupFlag::empty(), vec![mark_mark]);
+    Some(PositionLookup::MarkToMark(lookup))
   }
 
-  fn name(metadata: &Metadata) -> Name {
+  fn mark_array(marks: &[(u16, MarkClass, Point)]) -> (CoverageTable, MarkArray) {
+    let glyph_array = marks.iter().map(|(id, _, _)| GlyphId16::new(*id)).collect();
+    let coverage = CoverageTable::Format1(CoverageFormat1::new(glyph_array));
+
+    let records = marks
+      .iter()
+      .map(|(_, class, anchor)| MarkRecord::new(*class as u16, Builder::anchor(*anchor)))
+      .collect();
+
+    (coverage, MarkArray::new(records))
+  }
+
+  fn anchor(point: Point) -> AnchorTable {
+    AnchorTable::format_1(point.x, point.y)
+  }
+
+  fn gdef(&self) -> Option<Gdef> {
+    const BASE_GLYPH: u16 = 1;
+    const LIGATURE_GLYPH: u16 = 2;
+    const MARK_GLYPH: u16 = 3;
+
+    if self
+      .glyphs
+      .iter()
+      .all(|g| g.character.mark_class().is_none())
+    {
+      return None;
+    }
+
+    let glyph_class_def: ClassDef = self
+      .glyphs
+      .iter()
+      .enumerate()
+      .filter_map(|(id, g)| {
+        let class = match g.character {
+          UnicodeChar::NotDef | UnicodeChar::Layer(_) => return None,
+          UnicodeChar::Ligature(_) => LIGATURE_GLYPH,
+          c if c.mark_class().is_some() => MARK_GLYPH,
+          _ => BASE_GLYPH,
+        };
+        Some((GlyphId16::new(id as u16), class))
+      })
+      .collect();
+
+    Some(Gdef::new(Some(glyph_class_def), None, None, None))
+  }
+
+  fn name(
+    metadata: &Metadata,
+    names: &BTreeMap<String, String>,
+    feature_names: &HashMap<Tag, NameId>,
+  ) -> Name {
     let font_name = metadata.font_name();
     let font_subfamily = metadata.font_subfamily();
     let font_subfamily = font_subfamily.name();
@@ -511,11 +1356,38 @@ impl Builder {
     .iter()
     .flat_map(|(name_id, value)| Builder::name_record(*name_id, value))
     .collect();
+    records.extend(feature_names.iter().flat_map(|(tag, name_id)| {
+      let value = &names[&tag.to_string()];
+      Builder::name_record(*name_id, value)
+    }));
     records.sort();
 
     Name::new(records)
   }
 
+  // user facing names for the ssXX and cvXX features in use
+  fn feature_names(&self, names: &BTreeMap<String, String>) -> HashMap<Tag, NameId> {
+    const FIRST_FONT_SPECIFIC_NAME_ID: u16 = 256;
+
+    let tags: BTreeSet<Tag> = self
+      .glyphs
+      .iter()
+      .filter_map(|g| match g.character {
+        UnicodeChar::Alternate(_, tag, _) => Some(tag),
+        _ => None,
+      })
+      .collect();
+
+    names
+      .keys()
+      .filter(|feature| alternate::is_named_feature(feature))
+      .filter_map(|feature| Tag::new_checked(feature.as_bytes()).ok())
+      .filter(|tag| tags.contains(tag))
+      .enumerate()
+      .map(|(i, tag)| (tag, NameId::new(FIRST_FONT_SPECIFIC_NAME_ID + i as u16)))
+      .collect()
+  }
+
   fn post() -> Post {
     const ITALIC_ANGLE: Fixed = Fixed::from_i32(0);
 
@@ -564,11 +1436,16 @@ impl Builder {
     for (gid, g) in iter {
       let c = match g.character {
         UnicodeChar::NotDef => unreachable!(),
-        UnicodeChar::Char(c) => c,
+        UnicodeChar::Char(c) if u16::try_from(u32::from(c)).is_ok() => c,
+        UnicodeChar::Char(_)
+        | UnicodeChar::Alternate(..)
+        | UnicodeChar::Ligature(_)
+        | UnicodeChar::Layer(_) => break,
       };
       let unicode = c as u16;
       let gid = gid as i16;
-      let delta = gid - (unicode as i16);
+      // deltas are added modulo 65536
+      let delta = gid.wrapping_sub(unicode as i16);
 
       if !started {
         start_code = unicode;
@@ -602,6 +1479,42 @@ impl Builder {
     (start_codes, end_codes, id_deltas, id_range_offsets)
   }
 
+  // runs of characters and glyph ids that both go up by one,
+  //   only when some character is past the basic multilingual plane
+  fn cmap_groups(&self) -> Option<Vec<SequentialMapGroup>> {
+    let chars: Vec<(u32, u32)> = self
+      .glyphs
+      .iter()
+      .enumerate()
+      .filter_map(|(gid, g)| match g.character {
+        UnicodeChar::Char(c) => Some((u32::from(c), gid as u32)),
+        _ => None,
+      })
+      .collect();
+    if chars.iter().all(|(c, _)| u16::try_from(*c).is_ok()) {
+      return None;
+    }
+
+    let mut groups: Vec<SequentialMapGroup> = Vec::new();
+    let mut run: Option<(u32, u32, u32)> = None;
+    for (c, gid) in chars {
+      run = match run {
+        Some((start, end, start_gid)) if c == end + 1 && gid == start_gid + c - start => {
+          Some((start, c, start_gid))
+        }
+        Some((start, end, start_gid)) => {
+          groups.push(SequentialMapGroup::new(start, end, start_gid));
+          Some((c, c, gid))
+        }
+        None => Some((c, c, gid)),
+      };
+    }
+    if let Some((start, end, start_gid)) = run {
+      groups.push(SequentialMapGroup::new(start, end, start_gid));
+    }
+    Some(groups)
+  }
+
   fn name_record(name_id: NameId, value: &str) -> Vec<NameRecord> {
     const UNICODE_PLATFORM_ID: u16 = 0;
     const UNICODE_ENCODING_ID: u16 = 0;
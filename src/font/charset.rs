@@ -0,0 +1,196 @@
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use anyhow::{Context, Error, Result, bail, ensure};
+use constcat::concat;
+use serde::{Deserialize, Serialize};
+use strum::EnumString;
+
+const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FIRST: &str = r##"!"#$%&'()*+,-./"##;
+const BASIC_LATIN_DIGITS: &str = "0123456789";
+const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_SECOND: &str = ":;<=>?@";
+const BASIC_LATIN_UPPERCASE_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_THIRD: &str = r"[\]^_`";
+const BASIC_LATIN_LOWERCASE_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz";
+const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FOURTH: &str = "{|}~";
+
+const BASIC_LATIN: &str = concat!(
+  BASIC_LATIN_UPPERCASE_ALPHABET,
+  BASIC_LATIN_LOWERCASE_ALPHABET,
+  BASIC_LATIN_DIGITS,
+  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FIRST,
+  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_SECOND,
+  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_THIRD,
+  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FOURTH,
+);
+
+// euro 0x20ac snuck in as a supplement
+//   originally part of the currency unicode block
+const LATIN_SUPPLEMENT_PUNCTUATION_AND_SYMBOLS: &str = "¡¢£€¤¥¦§¨©ª«¬®¯°±²³´µ¶·¸¹º»¼½¾¿";
+const LATIN_SUPPLEMENT_LETTERS_FIRST: &str = "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ";
+const LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_FIRST: &str = "×";
+const LATIN_SUPPLEMENT_LETTERS_SECOND: &str = "ØÙÚÛÜÝÞßàáâãäåæçèéêëìíîïðñòóôõö";
+const LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_SECOND: &str = "÷";
+const LATIN_SUPPLEMENT_LETTERS_THIRD: &str = "øùúûüýþÿ";
+
+const LATIN_SUPPLEMENT: &str = concat!(
+  LATIN_SUPPLEMENT_PUNCTUATION_AND_SYMBOLS,
+  LATIN_SUPPLEMENT_LETTERS_FIRST,
+  LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_FIRST,
+  LATIN_SUPPLEMENT_LETTERS_SECOND,
+  LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_SECOND,
+  LATIN_SUPPLEMENT_LETTERS_THIRD
+);
+
+// long s 0x17f is left out
+const LATIN_A: &str = "ĀāĂăĄąĆćĈĉĊċČčĎďĐđĒēĔĕĖėĘęĚěĜĝĞğĠġĢģĤĥĦħĨĩĪīĬĭĮįİıĲĳĴĵĶķĸĹĺĻļĽľĿŀŁłŃńŅņŇňŉŊŋŌōŎŏŐőŒœŔŕŖŗŘřŚśŜŝŞşŠšŢţŤťŦŧŨũŪūŬŭŮůŰűŲųŴŵŶŷŸŹźŻżŽž";
+
+const LATIN_B_FIRST: char = '\u{0180}';
+const LATIN_B_LAST: char = '\u{024F}';
+
+const GREEK_UPPERCASE_ALPHABET: &str = "ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ";
+const GREEK_LOWERCASE_ALPHABET: &str = "αβγδεζηθικλμνξοπρςστυφχψω";
+const GREEK_ACCENTED_LETTERS: &str = "ΆΈΉΊΌΎΏΪΫάέήίόύώϊϋΐΰ";
+const GREEK_ACCENTS: &str = "΄΅";
+
+const GREEK: &str = concat!(
+  GREEK_UPPERCASE_ALPHABET,
+  GREEK_LOWERCASE_ALPHABET,
+  GREEK_ACCENTED_LETTERS,
+  GREEK_ACCENTS
+);
+
+const CYRILLIC_UPPERCASE_ALPHABET: &str = "АБВГДЕЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ";
+const CYRILLIC_LOWERCASE_ALPHABET: &str = "абвгдежзийклмнопрстуфхцчшщъыьэюя";
+const CYRILLIC_EXTENDED_LETTERS: &str = "ЀЁЂЃЄЅІЇЈЉЊЋЌЍЎЏѐёђѓєѕіїјљњћќѝўџҐґ";
+
+const CYRILLIC: &str = concat!(
+  CYRILLIC_UPPERCASE_ALPHABET,
+  CYRILLIC_LOWERCASE_ALPHABET,
+  CYRILLIC_EXTENDED_LETTERS
+);
+
+// soft hyphen 0xad is never drawn
+const SOFT_HYPHEN: char = '\u{00AD}';
+
+// the glyphs a project draws, in sheet order
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Charset(Vec<Block>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Block {
+  Preset(Preset),
+  // inclusive, written as "U+0370..U+03FF" or a single "U+2192"
+  Range(char, char),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, EnumString, strum::Display)]
+pub enum Preset {
+  #[strum(serialize = "basic-latin")]
+  BasicLatin,
+  // basic latin and the latin-1 supplement, as in ISO 8859-1
+  #[strum(serialize = "latin-1")]
+  Latin1,
+  #[strum(serialize = "latin-ext-a")]
+  LatinExtA,
+  #[strum(serialize = "latin-ext-b")]
+  LatinExtB,
+  #[strum(serialize = "greek")]
+  Greek,
+  #[strum(serialize = "cyrillic")]
+  Cyrillic,
+}
+
+impl Charset {
+  // every character once, first block first, leaving out
+  //   the spaces every font gets and anything that isn't drawn
+  pub fn chars(&self) -> Vec<char> {
+    let mut seen = HashSet::new();
+    self
+      .0
+      .iter()
+      .flat_map(|block| block.chars())
+      .filter(|c| !c.is_control() && !c.is_whitespace() && *c != SOFT_HYPHEN)
+      .filter(|c| seen.insert(*c))
+      .collect()
+  }
+
+  pub fn is_default(&self) -> bool {
+    *self == Charset::default()
+  }
+}
+
+impl Default for Charset {
+  fn default() -> Self {
+    Self(vec![
+      Block::Preset(Preset::Latin1),
+      Block::Preset(Preset::LatinExtA),
+    ])
+  }
+}
+
+impl Block {
+  fn chars(self) -> Box<dyn Iterator<Item = char>> {
+    match self {
+      Block::Preset(Preset::BasicLatin) => Box::new(BASIC_LATIN.chars()),
+      Block::Preset(Preset::Latin1) => {
+        Box::new(BASIC_LATIN.chars().chain(LATIN_SUPPLEMENT.chars()))
+      }
+      Block::Preset(Preset::LatinExtA) => Box::new(LATIN_A.chars()),
+      Block::Preset(Preset::LatinExtB) => Box::new(LATIN_B_FIRST..=LATIN_B_LAST),
+      Block::Preset(Preset::Greek) => Box::new(GREEK.chars()),
+      Block::Preset(Preset::Cyrillic) => Box::new(CYRILLIC.chars()),
+      Block::Range(first, last) => Box::new(first..=last),
+    }
+  }
+}
+
+impl TryFrom<String> for Block {
+  type Error = Error;
+
+  fn try_from(value: String) -> Result<Self> {
+    if let Ok(preset) = Preset::from_str(&value) {
+      return Ok(Block::Preset(preset));
+    }
+
+    let (first, last) = value.split_once("..").unwrap_or((&value, &value));
+    let first = code_point(first).with_context(|| format!("Unknown charset \"{value}\""))?;
+    let last = code_point(last).with_context(|| format!("Unknown charset \"{value}\""))?;
+    ensure!(
+      first <= last,
+      "Charset range \"{value}\" ends before it starts"
+    );
+
+    Ok(Block::Range(first, last))
+  }
+}
+
+impl From<Block> for String {
+  fn from(value: Block) -> Self {
+    value.to_string()
+  }
+}
+
+impl Display for Block {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Block::Preset(preset) => write!(f, "{preset}"),
+      Block::Range(first, last) if first == last => write!(f, "U+{:04X}", u32::from(*first)),
+      Block::Range(first, last) => {
+        write!(f, "U+{:04X}..U+{:04X}", u32::from(*first), u32::from(*last))
+      }
+    }
+  }
+}
+
+fn code_point(value: &str) -> Result<char> {
+  let Some(hex) = value
+    .strip_prefix("U+")
+    .or_else(|| value.strip_prefix("u+"))
+  else {
+    bail!("expected a code point like U+0041");
+  };
+  let code = u32::from_str_radix(hex, 16)?;
+  char::from_u32(code).context("not a unicode scalar value")
+}
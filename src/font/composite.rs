@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::decompose_canonical;
+
+use crate::font::{
+  dimensions::Dimensions,
+  point::Point,
+  unicode_char::{MarkClass, UnicodeChar},
+};
+
+const DOTLESS_I: char = 'ı';
+
+#[derive(Serialize, Deserialize)]
+pub struct Composites {
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  enabled: bool,
+  #[serde(default = "default_gap_above")]
+  gap_above: u16,
+  #[serde(default)]
+  gap_below: u16,
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  accents: BTreeMap<char, char>,
+}
+
+#[derive(Clone, Copy)]
+enum Transform {
+  None,
+  FlipVertical,
+  FlipHorizontal,
+  // a cup as wide and tall as the source, for a breve
+  Cup,
+  // the source twice side by side, for a double acute
+  Double,
+}
+
+impl Composites {
+  pub fn enabled(&self) -> bool {
+    self.enabled
+  }
+
+  pub fn is_default(&self) -> bool {
+    !self.enabled
+      && self.gap_above == default_gap_above()
+      && self.gap_below == 0
+      && self.accents.is_empty()
+  }
+
+  // builds an accented letter from its canonical decomposition,
+  //   stacking each mark on top of (or below) the previous result
+  pub fn compose(&self, c: char, glyphs: &HashMap<char, &[Point]>) -> Option<Vec<Point>> {
+    let mut decomposition = Vec::new();
+    decompose_canonical(c, |d| decomposition.push(d));
+    let (base, marks) = decomposition.split_first()?;
+    if marks.is_empty() {
+      return None;
+    }
+
+    let has_mark_above = marks.iter().any(|m| !Composites::is_below(*m));
+    let base = match base {
+      'i' if has_mark_above && glyphs.contains_key(&DOTLESS_I) => DOTLESS_I,
+      base => *base,
+    };
+
+    let mut pixels = glyphs.get(&base)?.to_vec();
+    for mark in marks {
+      let (accent, transform) = self
+        .accents(*mark)
+        .into_iter()
+        .find_map(|(accent, transform)| Some((glyphs.get(&accent)?, transform)))?;
+      let accent = Composites::transform(accent, transform);
+      let below = Composites::is_below(*mark);
+      let gap = i16::try_from(if below {
+        self.gap_below
+      } else {
+        self.gap_above
+      })
+      .ok()?;
+      pixels = Composites::attach(&pixels, &accent, below, gap)?;
+    }
+
+    Some(pixels)
+  }
+
+  // spacing glyphs standing in for a combining mark, the first one
+  //   drawn is used
+  fn accents(&self, mark: char) -> Vec<(char, Transform)> {
+    if let Some(accent) = self.accents.get(&mark) {
+      return vec![(*accent, Transform::None)];
+    }
+
+    match mark {
+      '\u{0300}' => vec![('`', Transform::None)],
+      '\u{0301}' => vec![('´', Transform::None)],
+      '\u{0302}' => vec![('^', Transform::None)],
+      '\u{0303}' => vec![('~', Transform::None)],
+      '\u{0304}' => vec![('¯', Transform::None)],
+      '\u{0306}' => vec![('˘', Transform::None), ('^', Transform::Cup)],
+      '\u{0307}' | '\u{0323}' => vec![('.', Transform::None)],
+      '\u{0308}' => vec![('¨', Transform::None)],
+      '\u{030A}' => vec![('°', Transform::None)],
+      '\u{030B}' => vec![('˝', Transform::None), ('´', Transform::Double)],
+      '\u{030C}' => vec![('^', Transform::FlipVertical)],
+      '\u{0327}' => vec![('¸', Transform::None)],
+      '\u{0328}' => vec![('¸', Transform::FlipHorizontal)],
+      _ => Vec::new(),
+    }
+  }
+
+  pub fn fits(pixels: &[Point], dimensions: &Dimensions) -> bool {
+    let width = i32::from(dimensions.tile_width());
+    let ascender = i32::from(dimensions.ascender_height());
+    let descender = i32::from(dimensions.descender_height());
+    pixels.iter().all(|p| {
+      (0..width).contains(&i32::from(p.x)) && (-descender..ascender).contains(&i32::from(p.y))
+    })
+  }
+
+  fn is_below(mark: char) -> bool {
+    UnicodeChar::Char(mark).mark_class() == Some(MarkClass::Bottom)
+  }
+
+  fn transform(pixels: &[Point], transform: Transform) -> Vec<Point> {
+    let (x_min, x_max, y_min, y_max) = bounds(pixels).unwrap_or_default();
+    match transform {
+      Transform::None => pixels.to_vec(),
+      Transform::FlipVertical => pixels
+        .iter()
+        .map(|p| Point::new(p.x, y_min + y_max - p.y))
+        .collect(),
+      Transform::FlipHorizontal => pixels
+        .iter()
+        .map(|p| Point::new(x_min + x_max - p.x, p.y))
+        .collect(),
+      // the sides on every row but the bottom one, which closes
+      //   the cup between them
+      Transform::Cup => (y_min..=y_max)
+        .flat_map(|y| {
+          if y == y_min && x_max - x_min > 1 {
+            (x_min + 1..x_max).map(|x| Point::new(x, y)).collect()
+          } else {
+            vec![Point::new(x_min, y), Point::new(x_max, y)]
+          }
+        })
+        .collect(),
+      Transform::Double => {
+        let width = x_max - x_min + 1;
+        pixels
+          .iter()
+          .flat_map(|p| [*p, Point::new(p.x + width, p.y)])
+          .collect()
+      }
+    }
+  }
+
+  fn attach(base: &[Point], accent: &[Point], below: bool, gap: i16) -> Option<Vec<Point>> {
+    let (bx_min, bx_max, by_min, by_max) = bounds(base)?;
+    let (ax_min, ax_max, ay_min, ay_max) = bounds(accent)?;
+
+    let dx = (bx_min + bx_max - ax_min - ax_max).div_euclid(2);
+    let dy = if below {
+      by_min - 1 - gap - ay_max
+    } else {
+      by_max + 1 + gap - ay_min
+    };
+
+    let mut pixels = base.to_vec();
+    pixels.extend(accent.iter().map(|p| Point::new(p.x + dx, p.y + dy)));
+    pixels.sort_by_key(|p| (p.y, p.x));
+    pixels.dedup();
+
+    Some(pixels)
+  }
+}
+
+impl Default for Composites {
+  fn default() -> Self {
+    Self {
+      enabled: false,
+      gap_above: default_gap_above(),
+      gap_below: 0,
+      accents: BTreeMap::new(),
+    }
+  }
+}
+
+fn bounds(pixels: &[Point]) -> Option<(i16, i16, i16, i16)> {
+  Some((
+    pixels.iter().map(|p| p.x).min()?,
+    pixels.iter().map(|p| p.x).max()?,
+    pixels.iter().map(|p| p.y).min()?,
+    pixels.iter().map(|p| p.y).max()?,
+  ))
+}
+
+fn default_gap_above() -> u16 {
+  1
+}
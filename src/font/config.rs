@@ -1,12 +1,44 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-use crate::font::{dimensions::Dimensions, kerning::Kerning, metadata::Metadata};
+use crate::font::{
+  alternate::Alternate, charset::Charset, composite::Composites, connectivity::Connectivity,
+  dimensions::Dimensions, forms::Forms, ink::Ink, kerning::Kerning, layout::Layout,
+  ligature::Ligature, metadata::Metadata, terminal::Terminal,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
   metadata: Metadata,
   dimensions: Dimensions,
   kerning: Kerning,
+  #[serde(default, skip_serializing_if = "Charset::is_default")]
+  charset: Charset,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  vertical: bool,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  ligatures: Vec<Ligature>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  alternates: Vec<Alternate>,
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  feature_names: BTreeMap<String, String>,
+  #[serde(default, skip_serializing_if = "Forms::is_default")]
+  forms: Forms,
+  #[serde(default, skip_serializing_if = "Composites::is_default")]
+  composites: Composites,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  marks: Vec<char>,
+  #[serde(default, skip_serializing_if = "Terminal::is_default")]
+  terminal: Terminal,
+  #[serde(default, skip_serializing_if = "Layout::is_default")]
+  layout: Layout,
+  #[serde(default, skip_serializing_if = "Ink::is_default")]
+  ink: Ink,
+  #[serde(default, skip_serializing_if = "Connectivity::is_default")]
+  connectivity: Connectivity,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  skip_invalid_glyphs: bool,
 }
 
 impl Config {
@@ -15,6 +47,19 @@ impl Config {
       metadata,
       dimensions,
       kerning,
+      charset: Charset::default(),
+      vertical: false,
+      ligatures: Vec::new(),
+      alternates: Vec::new(),
+      feature_names: BTreeMap::new(),
+      forms: Forms::default(),
+      composites: Composites::default(),
+      marks: Vec::new(),
+      terminal: Terminal::default(),
+      layout: Layout::default(),
+      ink: Ink::default(),
+      connectivity: Connectivity::default(),
+      skip_invalid_glyphs: false,
     }
   }
 
@@ -29,4 +74,56 @@ impl Config {
   pub fn kerning(&self) -> &Kerning {
     &self.kerning
   }
+
+  pub fn charset(&self) -> &Charset {
+    &self.charset
+  }
+
+  pub fn vertical(&self) -> bool {
+    self.vertical
+  }
+
+  pub fn ligatures(&self) -> &[Ligature] {
+    &self.ligatures
+  }
+
+  pub fn alternates(&self) -> &[Alternate] {
+    &self.alternates
+  }
+
+  pub fn feature_names(&self) -> &BTreeMap<String, String> {
+    &self.feature_names
+  }
+
+  pub fn forms(&self) -> &Forms {
+    &self.forms
+  }
+
+  pub fn composites(&self) -> &Composites {
+    &self.composites
+  }
+
+  pub fn marks(&self) -> &[char] {
+    &self.marks
+  }
+
+  pub fn terminal(&self) -> &Terminal {
+    &self.terminal
+  }
+
+  pub fn layout(&self) -> &Layout {
+    &self.layout
+  }
+
+  pub fn ink(&self) -> Ink {
+    self.ink
+  }
+
+  pub fn connectivity(&self) -> Connectivity {
+    self.connectivity
+  }
+
+  pub fn skip_invalid_glyphs(&self) -> bool {
+    self.skip_invalid_glyphs
+  }
 }
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Connectivity {
+  // pixels touching only at a corner are separate shapes
+  #[default]
+  Orthogonal,
+  // diagonal neighbours are one shape, joined by a small bridge
+  //   over the corner they share
+  Bridge,
+  // diagonal neighbours are separate contours, each cut back
+  //   from the corner they share so no two contours meet at a point
+  Split,
+}
+
+impl Connectivity {
+  #[allow(clippy::trivially_copy_pass_by_ref)]
+  pub fn is_default(&self) -> bool {
+    *self == Connectivity::default()
+  }
+}
@@ -1,166 +1,409 @@
-use std::collections::{HashSet, VecDeque};
+use std::{
+  collections::{HashMap, HashSet},
+  error::Error,
+  fmt::Display,
+};
 
-use multimap::MultiMap;
+use crate::font::{connectivity::Connectivity, point::Point, unicode_char::UnicodeChar};
 
-use crate::font::point::{Edge, Point};
+#[derive(Debug, Clone, Copy)]
+pub enum ContourError {
+  // the pixel below this corner is covered twice
+  Overlap(Point),
+  // the pixel below this corner is filled by a hole
+  Hole(Point),
+}
 
-pub fn contour(pixels: &[Point]) -> Vec<Vec<Point>> {
-  clusters(pixels)
-    .into_iter()
-    .map(|c| edges(&c))
-    .flat_map(|e| connect_edges(&e))
-    .collect()
+impl ContourError {
+  pub fn point(self) -> Point {
+    match self {
+      ContourError::Overlap(point) | ContourError::Hole(point) => point,
+    }
+  }
 }
 
-fn connect_edges(edges: &[Edge]) -> Vec<Vec<Point>> {
-  let mut contours: Vec<Vec<Point>> = Vec::new();
-  let map = reduce_edges(edges);
+impl Display for ContourError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      ContourError::Overlap(_) => write!(
+        f,
+        "outline fills differently under nonzero and even-odd rules"
+      ),
+      ContourError::Hole(_) => write!(f, "hole is not cut out of its outline"),
+    }
+  }
+}
 
-  let keys: Vec<Point> = map.keys().copied().collect();
-  let mut visited: HashSet<Edge> = HashSet::new();
-  for start in keys {
-    let mut contour: Vec<Point> = Vec::new();
+impl Error for ContourError {}
 
-    let mut last_edge: Option<Edge> = None;
-    let mut current = start;
-    loop {
-      let candidate = if let Some(last_edge) = last_edge
-        && map.is_vec(&current)
-      {
-        let choices = map.get_vec(&current).expect("Non cyclic");
-        if choices.iter().all(|p| visited.contains(&(current, *p))) {
-          break;
-        }
-
-        let last_sign = sign(last_edge.0, last_edge.1);
-        choices
-          .iter()
-          .filter(|p| !visited.contains(&(current, **p)))
-          .find(|p| sign(current, **p) == last_sign)
-          .copied()
-          .unwrap_or_else(|| {
-            panic!(
-              "No sign match with edges: {map:?}\nchoices: {choices:?}\nvisited: {visited:?}\ncurrent: {current}\nlast_sign: {last_sign}\nlast_edge: {last_edge:?}"
-            )
-          })
-      } else {
-        *map.get(&current).expect("Non cyclic")
-      };
+const EAST: (i16, i16) = (1, 0);
 
-      let edge = (current, candidate);
-      if visited.contains(&edge) {
-        break;
-      }
+// a contour error placed in the sheet
+#[derive(Debug)]
+pub struct GlyphError {
+  pub character: UnicodeChar,
+  // column and row of the tile, none for derived glyphs
+  pub tile: Option<(u32, u32)>,
+  // column and row, from the top left of the tile, of the pixel
+  //   right below the corner the outline failed at
+  pub pixel: (i32, i32),
+  pub error: ContourError,
+}
+
+impl Display for GlyphError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let (x, y) = self.pixel;
+    match self.tile {
+      Some((column, row)) => write!(
+        f,
+        "Could not trace '{}' in tile row {row}, column {column}: {} at pixel ({x}, {y})",
+        self.character, self.error
+      ),
+      None => write!(
+        f,
+        "Could not trace derived glyph '{}': {} at pixel ({x}, {y})",
+        self.character, self.error
+      ),
+    }
+  }
+}
+
+impl Error for GlyphError {}
+
+pub fn contour(
+  pixels: &[Point],
+  connectivity: Connectivity,
+) -> Result<Vec<Vec<Point>>, ContourError> {
+  let mut contours = Bitmap::new(pixels).trace(connectivity);
+
+  orient(&mut contours);
+  check_fill(&contours)?;
+
+  Ok(contours)
+}
+
+// outlines clockwise and the holes inside them counter-clockwise,
+//   as TrueType expects
+fn orient(contours: &mut [Vec<Point>]) {
+  let depths: Vec<usize> = contours
+    .iter()
+    .enumerate()
+    .map(|(i, contour)| {
+      // the middle of the first edge, which no other contour runs through
+      let (a, b) = (contour[0], contour[1 % contour.len()]);
+      let probe = (i32::from(a.x + b.x), i32::from(a.y + b.y));
+      contours
+        .iter()
+        .enumerate()
+        .filter(|(j, other)| *j != i && winding(other, probe) != 0)
+        .count()
+    })
+    .collect();
 
-      last_edge = Some(edge);
-      visited.insert(edge);
-      contour.push(current);
-      current = candidate;
+  for (contour, depth) in contours.iter_mut().zip(depths) {
+    let clockwise = area(contour) < 0;
+    if clockwise != depth.is_multiple_of(2) {
+      contour.reverse();
     }
+  }
+}
 
-    if !contour.is_empty() {
-      contours.push(contour);
+// with outlines clockwise every pixel must be wound once or not at all,
+//   anything else fills differently depending on the rasterizer
+fn check_fill(contours: &[Vec<Point>]) -> Result<(), ContourError> {
+  for (pixel, winding) in windings(contours, 1) {
+    let corner = Point::new(pixel.x, pixel.y + 1);
+    match winding {
+      0 | -1 => {}
+      w if w % 2 == 0 => return Err(ContourError::Overlap(corner)),
+      _ => return Err(ContourError::Hole(corner)),
     }
   }
 
-  contours
+  Ok(())
 }
 
-fn reduce_edges(edges: &[Edge]) -> MultiMap<Point, Point> {
-  let mut map: MultiMap<Point, Point> = edges.iter().copied().collect();
+// the pixels the outlines fill under the nonzero rule,
+//   `scale` units to a pixel
+pub fn rasterize(contours: &[Vec<Point>], scale: i16) -> HashSet<Point> {
+  windings(contours, scale)
+    .filter(|(_, winding)| *winding != 0)
+    .map(|(pixel, _)| pixel)
+    .collect()
+}
 
-  for (a, b) in edges {
-    if !map.contains_key(a) || map.is_vec(b) {
-      continue;
-    }
+// every pixel within the outlines' bounds and how often they wind around its center
+fn windings(contours: &[Vec<Point>], scale: i16) -> impl Iterator<Item = (Point, i32)> + '_ {
+  let points = || contours.iter().flatten();
+  let x_min = points()
+    .map(|p| p.x.div_euclid(scale))
+    .min()
+    .unwrap_or_default();
+  let x_max = points()
+    .map(|p| p.x.div_euclid(scale))
+    .max()
+    .unwrap_or_default();
+  let y_min = points()
+    .map(|p| p.y.div_euclid(scale))
+    .min()
+    .unwrap_or_default();
+  let y_max = points()
+    .map(|p| p.y.div_euclid(scale))
+    .max()
+    .unwrap_or_default();
+  let scale = i32::from(scale);
 
-    let mut droplist: Vec<Point> = vec![];
-    let mut mid = *b;
-    let mut end;
-    loop {
-      end = mid;
-      if map.is_vec(&mid) {
-        break;
+  (y_min..y_max).flat_map(move |y| {
+    (x_min..x_max).map(move |x| {
+      let center = (
+        scale * (2 * i32::from(x) + 1),
+        scale * (2 * i32::from(y) + 1),
+      );
+      let winding = contours.iter().map(|c| winding(c, center)).sum();
+      (Point::new(x, y), winding)
+    })
+  })
+}
+
+// twice the signed area, positive when counter-clockwise
+fn area(contour: &[Point]) -> i64 {
+  contour
+    .iter()
+    .zip(contour.iter().cycle().skip(1))
+    .map(|(a, b)| i64::from(a.x) * i64::from(b.y) - i64::from(b.x) * i64::from(a.y))
+    .sum()
+}
+
+// how often the contour winds around a point given in half units,
+//   counter-clockwise positive
+fn winding(contour: &[Point], (x, y): (i32, i32)) -> i32 {
+  contour
+    .iter()
+    .zip(contour.iter().cycle().skip(1))
+    .map(|(a, b)| {
+      let (ax, ay) = (2 * i64::from(a.x), 2 * i64::from(a.y));
+      let (bx, by) = (2 * i64::from(b.x), 2 * i64::from(b.y));
+      let (x, y) = (i64::from(x), i64::from(y));
+      if (ay <= y) == (by <= y) {
+        return 0;
       }
 
-      let c = *map.get(&mid).expect("Not cyclic");
-      if a.x == c.x || a.y == c.y {
-        droplist.push(mid);
-        mid = c;
+      // where the edge crosses the ray, relative to the point and
+      //   scaled by the edge's height, so its sign follows the edge
+      let crossing = (ax - x) * (by - ay) + (y - ay) * (bx - ax);
+      if by > ay && crossing > 0 {
+        1
+      } else if by < ay && crossing < 0 {
+        -1
       } else {
-        break;
+        0
       }
-    }
+    })
+    .sum()
+}
 
-    for p in &droplist {
-      map.remove(p);
-    }
+// every corner diagonal neighbours share that came out unresolved, a
+//   bridged corner must be filled on all four sides and a split one empty,
+//   sampled a quarter of `size` away where the pixel centres never reach
+pub fn corner_faults(
+  pixels: &[Point],
+  contours: &[Vec<Point>],
+  scale: i16,
+  size: i16,
+  bridge: bool,
+) -> Vec<Point> {
+  let filled: HashSet<Point> = pixels.iter().copied().collect();
+  let quadrants = [(-1, -1), (0, -1), (-1, 0), (0, 0)];
+  let corners: HashSet<Point> = pixels
+    .iter()
+    .flat_map(|p| quadrants.map(|(dx, dy)| Point::new(p.x - dx, p.y - dy)))
+    .filter(|corner| {
+      let [bottom_left, bottom_right, top_left, top_right] =
+        quadrants.map(|(dx, dy)| filled.contains(&Point::new(corner.x + dx, corner.y + dy)));
+      bottom_left == top_right && bottom_right == top_left && bottom_left != bottom_right
+    })
+    .collect();
 
-    if *b != end {
-      let b_pointer = map
-        .get_vec_mut(a)
-        .expect("Not cyclic")
-        .iter_mut()
-        .find(|x| *x == b)
-        .expect("Point is missing");
-      *b_pointer = end;
-    }
-  }
+  let offset = i32::from(size / 4);
+  let mut faults: Vec<Point> = corners
+    .into_iter()
+    .filter(|corner| {
+      let (x, y) = (
+        i32::from(corner.x) * i32::from(scale),
+        i32::from(corner.y) * i32::from(scale),
+      );
+      quadrants.iter().any(|(dx, dy)| {
+        let sample = (
+          2 * (x + (2 * i32::from(*dx) + 1) * offset),
+          2 * (y + (2 * i32::from(*dy) + 1) * offset),
+        );
+        let winding: i32 = contours.iter().map(|c| winding(c, sample)).sum();
+        (winding != 0) != bridge
+      })
+    })
+    .collect();
+  faults.sort_by_key(|p| (p.y, p.x));
+  faults
+}
 
-  map
+// widens every corner the outlines pass through twice into a square
+//   of `size`, so diagonal neighbours never meet at a single point
+pub fn bridge_corners(contours: &mut [Vec<Point>], size: i16) {
+  resolve_corners(contours, size, true);
 }
 
-fn sign(a: Point, b: Point) -> bool {
-  ((b.y - a.y) + (b.x - a.x)) > 0
+// cuts every corner the outlines pass through twice back by `size`
+//   along both its edges, so each contour closes on its own
+pub fn split_corners(contours: &mut [Vec<Point>], size: i16) {
+  resolve_corners(contours, size, false);
 }
 
-fn edges(cluster: &HashSet<Point>) -> Vec<Edge> {
-  cluster
-    .iter()
-    .flat_map(|p| {
-      [
-        (!cluster.contains(&p.above())).then(|| p.top_edge()),
-        (!cluster.contains(&p.right())).then(|| p.right_edge()),
-        (!cluster.contains(&p.below())).then(|| p.bottom_edge()),
-        (!cluster.contains(&p.left())).then(|| p.left_edge()),
-      ]
-    })
-    .flatten()
-    .collect()
+fn resolve_corners(contours: &mut [Vec<Point>], size: i16, bridge: bool) {
+  let mut counts: HashMap<Point, usize> = HashMap::new();
+  for point in contours.iter().flatten() {
+    *counts.entry(*point).or_default() += 1;
+  }
+
+  for contour in contours.iter_mut() {
+    let len = contour.len();
+    let mut resolved = Vec::with_capacity(len);
+    for (i, corner) in contour.iter().enumerate() {
+      if counts[corner] < 2 {
+        resolved.push(*corner);
+        continue;
+      }
+
+      // a bridged corner is traced around the empty side, so stepping
+      //   towards both neighbours fills that side in, while a split one
+      //   is traced around its pixel and the same steps cut its tip off
+      let previous = contour[(i + len - 1) % len];
+      let next = contour[(i + 1) % len];
+      let (px, py) = (
+        (previous.x - corner.x).signum() * size,
+        (previous.y - corner.y).signum() * size,
+      );
+      let (nx, ny) = (
+        (next.x - corner.x).signum() * size,
+        (next.y - corner.y).signum() * size,
+      );
+      resolved.push(Point::new(corner.x + px, corner.y + py));
+      if bridge {
+        resolved.push(Point::new(corner.x + px + nx, corner.y + py + ny));
+      }
+      resolved.push(Point::new(corner.x + nx, corner.y + ny));
+    }
+    *contour = resolved;
+  }
+}
+
+// the pixels of a glyph on a dense grid over their bounds
+struct Bitmap {
+  x_min: i16,
+  y_min: i16,
+  width: usize,
+  height: usize,
+  filled: Vec<bool>,
 }
 
-fn clusters(pixels: &[Point]) -> Vec<HashSet<Point>> {
-  let mut clusters = Vec::new();
-  let mut visited: HashSet<Point> = HashSet::new();
-  let points: HashSet<Point> = pixels.iter().copied().collect();
+#[allow(
+  clippy::cast_possible_truncation,
+  clippy::cast_possible_wrap,
+  clippy::cast_sign_loss
+)]
+impl Bitmap {
+  fn new(pixels: &[Point]) -> Self {
+    let x_min = pixels.iter().map(|p| p.x).min().unwrap_or_default();
+    let y_min = pixels.iter().map(|p| p.y).min().unwrap_or_default();
+    let width = pixels.iter().map(|p| (p.x - x_min + 1) as usize).max();
+    let height = pixels.iter().map(|p| (p.y - y_min + 1) as usize).max();
+    let (width, height) = (width.unwrap_or_default(), height.unwrap_or_default());
+
+    let mut bitmap = Self {
+      x_min,
+      y_min,
+      width,
+      height,
+      filled: vec![false; width * height],
+    };
+    for p in pixels {
+      if let Some(i) = bitmap.index(p.x, p.y) {
+        bitmap.filled[i] = true;
+      }
+    }
+
+    bitmap
+  }
+
+  fn index(&self, x: i16, y: i16) -> Option<usize> {
+    let x = usize::try_from(x - self.x_min).ok()?;
+    let y = usize::try_from(y - self.y_min).ok()?;
+    (x < self.width && y < self.height).then_some(y * self.width + x)
+  }
 
-  let mut queue: VecDeque<Point> = VecDeque::new();
-  for p in &points {
-    if visited.contains(p) {
-      continue;
+  fn get(&self, x: i16, y: i16) -> bool {
+    self.index(x, y).is_some_and(|i| self.filled[i])
+  }
+
+  // walks the cracks between filled and empty pixels from every bottom edge
+  //   not walked yet, one contour per walk
+  fn trace(&self, connectivity: Connectivity) -> Vec<Vec<Point>> {
+    let mut walked = vec![false; self.filled.len()];
+    let mut contours = Vec::new();
+
+    for (i, filled) in self.filled.iter().enumerate() {
+      let x = self.x_min + (i % self.width) as i16;
+      let y = self.y_min + (i / self.width) as i16;
+      if walked[i] || !filled || self.get(x, y - 1) {
+        continue;
+      }
+
+      contours.push(self.walk(Point::new(x, y), connectivity, &mut walked));
     }
-    visited.insert(*p);
-
-    let mut cluster: HashSet<Point> = HashSet::new();
-    cluster.insert(*p);
-    queue.push_back(*p);
-
-    while let Some(p) = queue.pop_front() {
-      let candidates: Vec<Point> = p
-        .adjacent()
-        .into_iter()
-        .filter(|p| points.contains(p) && !visited.contains(p))
-        .collect();
-
-      for c in candidates {
-        visited.insert(c);
-        cluster.insert(c);
-        queue.push_back(c);
+
+    contours
+  }
+
+  // keeps the filled pixels on the left and records the corners
+  //   where the walk turns, until it is back at `start` heading east
+  fn walk(&self, start: Point, connectivity: Connectivity, walked: &mut [bool]) -> Vec<Point> {
+    let mut corners = vec![start];
+    let (mut corner, mut direction) = (start, EAST);
+    loop {
+      // bottom edges are walked east and belong to the pixel above them
+      if direction == EAST
+        && let Some(i) = self.index(corner.x, corner.y)
+      {
+        walked[i] = true;
       }
+
+      corner = Point::new(corner.x + direction.0, corner.y + direction.1);
+      let next = self.turn(corner, direction, connectivity);
+      if corner == start && next == EAST {
+        break;
+      }
+      if next != direction {
+        corners.push(corner);
+      }
+      direction = next;
     }
 
-    clusters.push(cluster);
+    corners
   }
 
-  clusters
+  // where the walk continues from a corner, from the two pixels ahead of it
+  fn turn(&self, corner: Point, (dx, dy): (i16, i16), connectivity: Connectivity) -> (i16, i16) {
+    let left = (-dy, dx);
+    let right = (dy, -dx);
+    let ahead = |(x, y): (i16, i16)| self.get(corner.x + x.min(0), corner.y + y.min(0));
+
+    match (ahead((dx - dy, dy + dx)), ahead((dx + dy, dy - dx))) {
+      (true, false) => (dx, dy),
+      (true, true) => right,
+      // pixels touching only at this corner, turning right carries the walk
+      //   on into the neighbour, turning left closes it around each of them,
+      //   which split corners are pulled apart from once scaled
+      (false, true) if connectivity == Connectivity::Bridge => right,
+      (false, _) => left,
+    }
+  }
 }
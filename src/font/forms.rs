@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+// raised to cap height in the case feature
+const CASE_CHARACTERS: &str = "-()[]{}@«»¡¿·";
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Forms {
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  small_caps: bool,
+  #[serde(default, skip_serializing_if = "is_zero")]
+  case_shift: u16,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  case_characters: Option<String>,
+}
+
+impl Forms {
+  pub fn small_caps(&self) -> bool {
+    self.small_caps
+  }
+
+  pub fn case_shift(&self) -> u16 {
+    self.case_shift
+  }
+
+  pub fn case_characters(&self) -> &str {
+    self.case_characters.as_deref().unwrap_or(CASE_CHARACTERS)
+  }
+
+  pub fn is_default(&self) -> bool {
+    !self.small_caps && self.case_shift == 0 && self.case_characters.is_none()
+  }
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(v: &u16) -> bool {
+  *v == 0
+}
@@ -1,70 +1,23 @@
-use constcat::concat;
-use read_fonts::tables::glyf::CurvePoint;
-use write_fonts::tables::glyf::{Bbox, SimpleGlyph};
-
-use crate::font::{point::Point, unicode_char::UnicodeChar};
+use std::collections::HashSet;
 
-pub mod sheet_unicode_blocks {
-  const BASIC_LATIN_CODE_POINTS: u32 = 128;
-  const BASIC_LATIN_CONTROLS: u32 = 33;
-  const BASIC_LATIN_NON_RENDERING: u32 = 1; // space 0x20
-  pub const BASIC_LATIN_SUPPORTED: u32 =
-    BASIC_LATIN_CODE_POINTS - BASIC_LATIN_CONTROLS - BASIC_LATIN_NON_RENDERING;
-
-  const LATIN_SUPPLEMENT_CODE_POINTS: u32 = 128;
-  const LATIN_SUPPLEMENT_CONTROLS: u32 = 32;
-  const LATIN_SUPPLEMENT_NON_RENDERING: u32 = 2; // nbsp 0xa0, soft-hyphen 0xad
-  pub const LATIN_SUPPLEMENT_SUPPORTED: u32 =
-    LATIN_SUPPLEMENT_CODE_POINTS - LATIN_SUPPLEMENT_CONTROLS - LATIN_SUPPLEMENT_NON_RENDERING;
+use read_fonts::{tables::glyf::CurvePoint, types::Tag};
+use write_fonts::tables::glyf::{Bbox, SimpleGlyph};
 
-  const LATIN_A_CODE_POINTS: u32 = 128;
-  const LATIN_A_CONTROLS: u32 = 0;
-  const LATIN_A_UNSUPPORTED: u32 = 1; // long s 0x17f
-  pub const LATIN_A_SUPPORTED: u32 = LATIN_A_CODE_POINTS - LATIN_A_CONTROLS - LATIN_A_UNSUPPORTED;
+use crate::font::{
+  charset::Charset,
+  contour::{corner_faults, rasterize},
+  point::Point,
+  sheet::colors,
+  unicode_char::{MarkClass, UnicodeChar},
+};
 
-  pub const CURRENCY_SUPPORTED: u32 = 1; // euro 0x20ac
-  pub const NOT_DEF: u32 = 1;
-}
+// punctuation laid on its side in vertical text
+const VERTICAL_ROTATED: &str = "()-<>[]_{}~«»";
 
-const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FIRST: &str = r##"!"#$%&'()*+,-./"##;
-const BASIC_LATIN_DIGITS: &str = "0123456789";
-const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_SECOND: &str = ":;<=>?@";
-const BASIC_LATIN_UPPERCASE_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_THIRD: &str = r"[\]^_`";
-const BASIC_LATIN_LOWERCASE_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz";
-const BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FOURTH: &str = "{|}~";
-
-const BASIC_LATIN: &str = concat!(
-  BASIC_LATIN_UPPERCASE_ALPHABET,
-  BASIC_LATIN_LOWERCASE_ALPHABET,
-  BASIC_LATIN_DIGITS,
-  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FIRST,
-  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_SECOND,
-  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_THIRD,
-  BASIC_LATIN_PUNCTUATION_AND_SYMBOLS_FOURTH,
-);
-
-// euro 0x20ac snuck in as a supplement
-//   originally part of the currency unicode block
-const LATIN_SUPPLEMENT_PUNCTUATION_AND_SYMBOLS: &str = "¡¢£€¤¥¦§¨©ª«¬®¯°±²³´µ¶·¸¹º»¼½¾¿";
-const LATIN_SUPPLEMENT_LETTERS_FIRST: &str = "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ";
-const LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_FIRST: &str = "×";
-const LATIN_SUPPLEMENT_LETTERS_SECOND: &str = "ØÙÚÛÜÝÞßàáâãäåæçèéêëìíîïðñòóôõö";
-const LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_SECOND: &str = "÷";
-const LATIN_SUPPLEMENT_LETTERS_THIRD: &str = "øùúûüýþÿ";
-
-const LATIN_SUPPLEMENT: &str = concat!(
-  LATIN_SUPPLEMENT_PUNCTUATION_AND_SYMBOLS,
-  LATIN_SUPPLEMENT_LETTERS_FIRST,
-  LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_FIRST,
-  LATIN_SUPPLEMENT_LETTERS_SECOND,
-  LATIN_SUPPLEMENT_MATHEMATICAL_OPERATORS_SECOND,
-  LATIN_SUPPLEMENT_LETTERS_THIRD
-);
-
-const LATIN_A: &str = "ĀāĂăĄąĆćĈĉĊċČčĎďĐđĒēĔĕĖėĘęĚěĜĝĞğĠġĢģĤĥĦħĨĩĪīĬĭĮįİıĲĳĴĵĶķĸĹĺĻļĽľĿŀŁłŃńŅņŇňŉŊŋŌōŎŏŐőŒœŔŕŖŗŘřŚśŜŝŞşŠšŢţŤťŦŧŨũŪūŬŭŮůŰűŲųŴŵŶŷŸŹźŻżŽž";
-
-const SUPPORTED_GLYPHS: &str = concat!(BASIC_LATIN, LATIN_SUPPLEMENT, LATIN_A);
+pub const VERT_TAG: Tag = Tag::new(b"vert");
+pub const SMCP_TAG: Tag = Tag::new(b"smcp");
+pub const C2SC_TAG: Tag = Tag::new(b"c2sc");
+pub const CASE_TAG: Tag = Tag::new(b"case");
 
 #[derive(Debug, Eq)]
 pub struct Glyph {
@@ -72,6 +25,16 @@ pub struct Glyph {
   pub bbox: Bbox,
   pub contours: Vec<Vec<Point>>,
   pub pixels: Vec<Point>,
+  pub anchors: Vec<Point>,
+  pub layers: Vec<Layer>,
+}
+
+// the pixels of one colour in a colour glyph
+#[derive(Debug, PartialEq, Eq)]
+pub struct Layer {
+  pub color: [u8; 4],
+  pub contours: Vec<Vec<Point>>,
+  pub pixels: Vec<Point>,
 }
 
 impl Glyph {
@@ -81,11 +44,53 @@ impl Glyph {
       bbox: Glyph::create_bbox(&contours),
       contours,
       pixels,
+      anchors: Vec::new(),
+      layers: Vec::new(),
     }
   }
 
-  pub fn glyphs() -> Vec<UnicodeChar> {
-    let mut glyphs: Vec<_> = SUPPORTED_GLYPHS.chars().map(UnicodeChar::Char).collect();
+  pub fn with_anchors(mut self, anchors: Vec<Point>) -> Self {
+    self.anchors = anchors;
+    self
+  }
+
+  pub fn with_layers(mut self, layers: Vec<Layer>) -> Self {
+    self.layers = layers;
+    self
+  }
+
+  // where a mark attaches, and where further marks stack onto it
+  pub fn mark_anchors(&self) -> Option<(Point, Option<Point>)> {
+    let lowest = *self.anchors.iter().min_by_key(|p| p.y)?;
+    let highest = *self.anchors.iter().max_by_key(|p| p.y)?;
+    let (attach, stack) = match self.character.mark_class()? {
+      MarkClass::Top => (lowest, highest),
+      MarkClass::Bottom => (highest, lowest),
+    };
+
+    Some((attach, (stack != attach).then_some(stack)))
+  }
+
+  // anchors above the middle of the glyph take top marks, the rest bottom marks
+  pub fn base_anchors(&self) -> [Option<Point>; 2] {
+    let middle = i32::from(self.bbox.y_min) + i32::from(self.bbox.y_max);
+    let top = self
+      .anchors
+      .iter()
+      .filter(|p| i32::from(p.y) * 2 >= middle)
+      .max_by_key(|p| p.y);
+    let bottom = self
+      .anchors
+      .iter()
+      .filter(|p| i32::from(p.y) * 2 < middle)
+      .min_by_key(|p| p.y);
+
+    [top.copied(), bottom.copied()]
+  }
+
+  // the sheet's glyphs in tile order, then the spaces that have no tile
+  pub fn glyphs(charset: &Charset) -> Vec<UnicodeChar> {
+    let mut glyphs: Vec<_> = charset.chars().into_iter().map(UnicodeChar::Char).collect();
     glyphs.push(UnicodeChar::NotDef);
     glyphs.push(UnicodeChar::UNICODE_SPACE);
     glyphs.push(UnicodeChar::UNICODE_NBSP);
@@ -93,11 +98,92 @@ impl Glyph {
     glyphs
   }
 
+  pub fn vertical_alternate(&self) -> Option<UnicodeChar> {
+    match self.character {
+      UnicodeChar::Char(c) if VERTICAL_ROTATED.contains(c) => {
+        Some(UnicodeChar::Alternate(c, VERT_TAG, 0))
+      }
+      _ => None,
+    }
+  }
+
+  pub fn rotated_pixels(&self, ascender: i16, descender: i16) -> Vec<Point> {
+    let (Some(x_min), Some(x_max)) = (
+      self.pixels.iter().map(|p| p.x).min(),
+      self.pixels.iter().map(|p| p.x).max(),
+    ) else {
+      return Vec::new();
+    };
+
+    // quarter turn clockwise, centered vertically in the tile
+    let width = x_max - x_min + 1;
+    let top = ascender - 1 - (ascender + descender - width) / 2;
+    self
+      .pixels
+      .iter()
+      .map(|p| Point::new(p.y + descender, top - (p.x - x_min)))
+      .collect()
+  }
+
+  // nearest neighbour scaling about the origin of the glyph
+  pub fn scaled_pixels(&self, numerator: i16, denominator: i16) -> Vec<Point> {
+    let (Some(x_min), Some(x_max), Some(y_min), Some(y_max)) = (
+      self.pixels.iter().map(|p| p.x).min(),
+      self.pixels.iter().map(|p| p.x).max(),
+      self.pixels.iter().map(|p| p.y).min(),
+      self.pixels.iter().map(|p| p.y).max(),
+    ) else {
+      return Vec::new();
+    };
+
+    let width = (x_max - x_min + 1) * numerator / denominator;
+    let y_start = (y_min * numerator).div_euclid(denominator);
+    let y_end = ((y_max + 1) * numerator).div_euclid(denominator);
+
+    let mut pixels = Vec::new();
+    for y in y_start..y_end {
+      for x in 0..width {
+        let source = Point::new(
+          x_min + x * denominator / numerator,
+          (y * denominator).div_euclid(numerator),
+        );
+        if self.pixels.contains(&source) {
+          pixels.push(Point::new(x_min + x, y));
+        }
+      }
+    }
+
+    pixels
+  }
+
+  pub fn shifted_pixels(&self, shift: i16) -> Vec<Point> {
+    self
+      .pixels
+      .iter()
+      .map(|p| Point::new(p.x, p.y + shift))
+      .collect()
+  }
+
+  pub fn height(&self) -> Option<i16> {
+    self.pixels.iter().map(|p| p.y + 1).max()
+  }
+
+  // `scale` units to a pixel, 1 until the glyph is scaled
+  pub fn outline_diff(&self, scale: i16) -> Option<String> {
+    outline_diff(&self.pixels, &self.contours, scale)
+  }
+
+  // corners between diagonal neighbours left unresolved, once scaled
+  pub fn corner_faults(&self, scale: i16, size: i16, bridge: bool) -> Vec<Point> {
+    corner_faults(&self.pixels, &self.contours, scale, size, bridge)
+  }
+
   pub fn scale_data(&mut self, scale: i16) {
     self
       .contours
       .iter_mut()
       .for_each(|contour| contour.iter_mut().for_each(|point| point.scale(scale)));
+    self.anchors.iter_mut().for_each(|point| point.scale(scale));
     self.bbox = Bbox {
       x_min: self.bbox.x_min * scale,
       y_min: self.bbox.y_min * scale,
@@ -142,6 +228,50 @@ impl Glyph {
   }
 }
 
+impl Layer {
+  // drawn in the glyph colour, so it takes the colour of the text
+  pub fn is_foreground(&self) -> bool {
+    let [r, g, b, a] = self.color;
+    [r, g, b] == colors::GLYPH_COLOR && a == u8::MAX
+  }
+
+  pub fn outline_diff(&self) -> Option<String> {
+    outline_diff(&self.pixels, &self.contours, 1)
+  }
+}
+
+// the drawn pixels against the ones the contours fill, top row first,
+//   or None when they match
+fn outline_diff(pixels: &[Point], contours: &[Vec<Point>], scale: i16) -> Option<String> {
+  let drawn: HashSet<Point> = pixels.iter().copied().collect();
+  let filled = rasterize(contours, scale);
+  if drawn == filled {
+    return None;
+  }
+
+  let points = || drawn.iter().chain(&filled);
+  let x_min = points().map(|p| p.x).min()?;
+  let x_max = points().map(|p| p.x).max()?;
+  let y_min = points().map(|p| p.y).min()?;
+  let y_max = points().map(|p| p.y).max()?;
+
+  let mut diff = String::new();
+  for y in (y_min..=y_max).rev() {
+    for x in x_min..=x_max {
+      let pixel = Point::new(x, y);
+      diff.push(match (drawn.contains(&pixel), filled.contains(&pixel)) {
+        (true, true) => '#',
+        (false, false) => '.',
+        (false, true) => '+',
+        (true, false) => '-',
+      });
+    }
+    diff.push('\n');
+  }
+
+  Some(diff)
+}
+
 impl From<&Glyph> for SimpleGlyph {
   fn from(val: &Glyph) -> Self {
     let contours = val
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+use crate::font::sheet::colors;
+
+// how far past the rule a pixel may be to still count as almost ink
+const NEAR_INK: u8 = 48;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Ink {
+  // opaque pixels of exactly the glyph colour
+  #[default]
+  Exact,
+  // mostly opaque pixels at most `threshold` bright
+  Luminance {
+    #[serde(default = "default_luminance")]
+    threshold: u8,
+  },
+  // pixels at least `threshold` opaque, whatever their colour,
+  //   for glyphs drawn on a transparent layer
+  Alpha {
+    #[serde(default = "default_alpha")]
+    threshold: u8,
+  },
+  // every pixel off the sheet background, each colour becoming
+  //   a layer of a colour glyph
+  Color,
+}
+
+impl Ink {
+  #[allow(clippy::trivially_copy_pass_by_ref)]
+  pub fn is_default(&self) -> bool {
+    *self == Ink::default()
+  }
+
+  pub fn is_ink(self, [r, g, b, a]: [u8; 4]) -> bool {
+    match self {
+      Ink::Exact => [r, g, b] == colors::GLYPH_COLOR && a == u8::MAX,
+      Ink::Luminance { threshold } => a >= 128 && luminance([r, g, b]) <= threshold,
+      Ink::Alpha { threshold } => a >= threshold.max(1),
+      Ink::Color => a > 0,
+    }
+  }
+
+  // not ink, but close enough that it was probably meant to be
+  pub fn is_near(self, pixel: [u8; 4]) -> bool {
+    let [r, g, b, a] = pixel;
+    if a == 0 || self.is_ink(pixel) {
+      return false;
+    }
+
+    match self {
+      Ink::Exact => r.max(g).max(b) <= NEAR_INK,
+      Ink::Luminance { threshold } => luminance([r, g, b]) <= threshold.saturating_add(NEAR_INK),
+      Ink::Alpha { threshold } => a >= threshold.saturating_sub(NEAR_INK),
+      Ink::Color => false,
+    }
+  }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn luminance([r, g, b]: [u8; 3]) -> u8 {
+  ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000) as u8
+}
+
+fn default_luminance() -> u8 {
+  64
+}
+
+fn default_alpha() -> u8 {
+  128
+}
@@ -1,16 +1,38 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+  iter,
+};
 
+use anyhow::{Result, bail};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use strum::Display;
+use unicode_normalization::char::decompose_canonical;
 
-use crate::font::{glyphs::Glyph, point::Point};
+use crate::font::{
+  charset::Charset,
+  glyphs::Glyph,
+  kerning_strategy::{KerningStrategy, Profile, Reference},
+  point::Point,
+};
 
 #[derive(Deserialize, Serialize)]
 pub struct Kerning {
   #[serde(default = "default_true", skip_serializing_if = "is_true")]
   enabled: bool,
   limit: i16,
+  #[serde(default, skip_serializing_if = "KerningStrategy::is_default")]
+  strategy: KerningStrategy,
   #[serde(default, skip_serializing_if = "Vec::is_empty")]
   pairs: Vec<KerningPair>,
+  #[serde(default = "default_true", skip_serializing_if = "is_true")]
+  classes: bool,
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  groups: BTreeMap<String, String>,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  legacy_table: bool,
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  alternates: BTreeMap<char, KerningAlternates>,
 }
 
 impl Kerning {
@@ -18,77 +40,215 @@ impl Kerning {
     Self {
       enabled,
       limit,
+      strategy: KerningStrategy::default(),
       pairs,
+      classes: true,
+      groups: BTreeMap::new(),
+      legacy_table: false,
+      alternates: BTreeMap::new(),
     }
   }
 
-  pub fn kern(&self, glyphs: &[Glyph]) -> HashMap<(char, char), i16> {
+  pub fn classes(&self) -> bool {
+    self.classes
+  }
+
+  pub fn groups(&self) -> &BTreeMap<String, String> {
+    &self.groups
+  }
+
+  pub fn legacy_table(&self) -> bool {
+    self.legacy_table
+  }
+
+  pub fn kern(&self, glyphs: &[Glyph], charset: &Charset) -> Result<HashMap<(char, char), i16>> {
+    Ok(
+      self
+        .kern_entries(glyphs, charset)?
+        .into_iter()
+        .map(|(pair, entry)| (pair, entry.value))
+        .collect(),
+    )
+  }
+
+  // every kerned pair along with where its value came from
+  pub fn kern_entries(
+    &self,
+    glyphs: &[Glyph],
+    charset: &Charset,
+  ) -> Result<HashMap<(char, char), KerningEntry>> {
     if !self.enabled {
-      return HashMap::with_capacity(0);
+      return Ok(HashMap::with_capacity(0));
     }
 
-    let mut result: HashMap<(char, char), i16> = HashMap::new();
+    self.validate_groups(glyphs, charset)?;
 
-    let chars: HashSet<char> = glyphs
+    let mut result: HashMap<(char, char), KerningEntry> = HashMap::new();
+
+    let kerned: Vec<&Glyph> = glyphs
+      .iter()
+      .filter(|g| g.character.should_kern())
+      .collect();
+    let chars: BTreeSet<char> = kerned
       .iter()
       .filter_map(|g| g.character.try_into().ok())
       .collect();
-    let map: HashMap<(char, char), (bool, bool, i16)> = self
+    let manual: HashSet<(char, char)> = self
       .pairs
       .iter()
-      .map(|pair| {
-        (
-          (pair.left, pair.right),
-          (pair.enabled, pair.alts, pair.value),
-        )
+      .filter_map(|pair| Some((pair.left.glyph()?, pair.right.glyph()?)))
+      .collect();
+
+    let profiles: Vec<Profile> = kerned.iter().map(|g| Profile::new(&g.pixels)).collect();
+    let reference = Reference::new(glyphs);
+
+    // one task per left glyph, collected in glyph order
+    let auto: Vec<Vec<((char, char), KerningEntry)>> = kerned
+      .par_iter()
+      .zip(&profiles)
+      .map(|(left, left_profile)| {
+        let char_l: char = left.character.try_into().unwrap();
+        kerned
+          .iter()
+          .zip(&profiles)
+          .filter_map(|(right, right_profile)| {
+            let char_r: char = right.character.try_into().unwrap();
+            if manual.contains(&(char_l, char_r)) {
+              return None;
+            }
+
+            let kerning = self.kern_pair(left, right, (left_profile, right_profile), &reference);
+            let entry = KerningEntry::new(kerning, KerningOrigin::Auto, kerning <= self.limit);
+            (kerning != 0).then_some(((char_l, char_r), entry))
+          })
+          .collect()
       })
       .collect();
+    result.extend(auto.into_iter().flatten());
 
-    for left in glyphs {
-      if !left.character.should_kern() {
-        continue;
-      }
+    let mut pairs: Vec<&KerningPair> = self.pairs.iter().collect();
+    // group–group, then glyph–group, then glyph–glyph, each overriding the last
+    pairs.sort_by_key(|pair| std::cmp::Reverse(pair.group_count()));
 
-      for right in glyphs {
-        if !right.character.should_kern() {
-          continue;
+    // a disabled pair leaves its glyphs unkerned, whatever the less
+    //   specific pairs or auto kerning say
+    for pair in pairs
+      .iter()
+      .filter(|pair| pair.group_count() > 0 || !pair.enabled)
+    {
+      for char_l in self.members(&pair.left, &chars)? {
+        for char_r in self.members(&pair.right, &chars)? {
+          if pair.enabled {
+            let entry = KerningEntry::new(pair.value, KerningOrigin::Group, false);
+            result.insert((char_l, char_r), entry);
+          } else {
+            result.remove(&(char_l, char_r));
+          }
         }
+      }
+    }
 
-        let char_l: char = left.character.try_into().unwrap();
-        let char_r: char = right.character.try_into().unwrap();
-        if let Some((enabled, alts, value)) = map.get(&(char_l, char_r)) {
-          if !enabled {
-            continue;
-          }
+    let glyph_pairs: Vec<(char, char, &KerningPair)> = pairs
+      .iter()
+      .filter(|pair| pair.enabled)
+      .filter_map(|pair| Some((pair.left.glyph()?, pair.right.glyph()?, *pair)))
+      .filter(|(left, right, _)| chars.contains(left) && chars.contains(right))
+      .collect();
 
-          let kerning = *value;
-          if kerning == 0 {
-            continue;
-          }
+    // alternates first so a manual pair on an alternate itself wins
+    for (left, right, pair) in &glyph_pairs {
+      if !pair.alts || pair.value == 0 {
+        continue;
+      }
 
-          result.insert((char_l, char_r), kerning);
-          if *alts {
-            let l_alts = Kerning::alts(char_l);
-            let r_alts = Kerning::alts(char_r);
-            for char_l in l_alts.unwrap_or("").chars().filter(|c| chars.contains(c)) {
-              for char_r in r_alts.unwrap_or("").chars().filter(|c| chars.contains(c)) {
-                result.insert((char_l, char_r), kerning);
-              }
-            }
-          }
-        } else {
-          let kerning = self.kern_pair(left, right);
-          if kerning != 0 {
-            result.insert((char_l, char_r), kerning);
+      let lefts = self.alts(*left, &chars);
+      let rights = self.alts(*right, &chars);
+      for char_l in iter::once(*left).chain(lefts.iter().copied()) {
+        for char_r in iter::once(*right).chain(rights.iter().copied()) {
+          if !manual.contains(&(char_l, char_r)) {
+            let entry = KerningEntry::new(pair.value, KerningOrigin::Alts, false);
+            result.insert((char_l, char_r), entry);
           }
         }
       }
     }
 
-    result
+    for (left, right, pair) in glyph_pairs {
+      let entry = KerningEntry::new(pair.value, KerningOrigin::Manual, false);
+      result.insert((left, right), entry);
+    }
+
+    result.retain(|_, entry| entry.value != 0);
+
+    Ok(result)
   }
 
-  fn kern_pair(&self, left: &Glyph, right: &Glyph) -> i16 {
+  // group names start with '@' and only hold glyphs the sheet can draw,
+  //   members left undrawn are skipped with a warning
+  fn validate_groups(&self, glyphs: &[Glyph], charset: &Charset) -> Result<()> {
+    let supported: HashSet<char> = Glyph::glyphs(charset)
+      .into_iter()
+      .filter_map(|c| c.try_into().ok())
+      .collect();
+    let drawn: HashSet<char> = glyphs
+      .iter()
+      .filter_map(|g| g.character.try_into().ok())
+      .collect();
+
+    for (name, members) in &self.groups {
+      if !name.starts_with('@') || name.len() < 2 {
+        bail!("Kerning group \"{name}\" must be named like \"@name\"");
+      }
+      if let Some(c) = members.chars().find(|c| !supported.contains(c)) {
+        bail!("Kerning group \"{name}\" contains '{c}', which is not in the sheet");
+      }
+
+      let undrawn: String = members.chars().filter(|c| !drawn.contains(c)).collect();
+      if !undrawn.is_empty() {
+        eprintln!("Warning: kerning group \"{name}\" contains \"{undrawn}\", which is not drawn");
+      }
+    }
+
+    Ok(())
+  }
+
+  fn members(&self, side: &KerningSide, chars: &BTreeSet<char>) -> Result<Vec<char>> {
+    let members = match side {
+      KerningSide::Glyph(c) => vec![*c],
+      KerningSide::Group(name) => match self.groups.get(name) {
+        Some(members) => members.chars().collect(),
+        None => bail!("Unknown kerning group \"{name}\""),
+      },
+    };
+
+    Ok(members.into_iter().filter(|c| chars.contains(c)).collect())
+  }
+
+  // slides `right` towards `left` one pixel at a time until the strategy
+  //   says stop, or `limit` is hit
+  fn kern_pair(
+    &self,
+    left: &Glyph,
+    right: &Glyph,
+    profiles: (&Profile, &Profile),
+    reference: &Reference,
+  ) -> i16 {
+    if self.limit >= 0 {
+      return 0;
+    }
+
+    let x_max = i32::from(left.bbox.x_max);
+    let Some(steps) = self
+      .strategy
+      .steps(x_max, profiles.0, profiles.1, reference)
+    else {
+      return self.kern_pair_scan(left, right);
+    };
+
+    i16::try_from(-steps).map_or(self.limit, |kerning| kerning.max(self.limit))
+  }
+
+  fn kern_pair_scan(&self, left: &Glyph, right: &Glyph) -> i16 {
     let mut kerning = 0i16;
     let mut offset = left.bbox.x_max + 1;
 
@@ -110,40 +270,100 @@ impl Kerning {
     kerning
   }
 
-  fn alts(c: char) -> Option<&'static str> {
-    match c {
-      'A' => Some("ÀÁÂÃÄÅ"),
-      'a' => Some("àáâãäå"),
-      'C' => Some("Ç"),
-      'c' => Some("ç"),
-      'E' => Some("ÈÉÊË"),
-      'e' => Some("èéêë"),
-      'I' => Some("ÌÍÎÏ"),
-      'i' => Some("ìíîï"),
-      'N' => Some("Ñ"),
-      'n' => Some("ñ"),
-      'O' => Some("ÒÓÔÕÖ"),
-      'o' => Some("òóôõö"),
-      'U' => Some("ÙÚÛÜ"),
-      'u' => Some("ùúûü"),
-      'Y' => Some("Ÿ"),
-      'y' => Some("ÿ"),
-      _ => None,
+  // every glyph whose canonical decomposition starts with `c`,
+  //   adjusted by the configured additions and removals
+  fn alts(&self, c: char, chars: &BTreeSet<char>) -> BTreeSet<char> {
+    let mut alts: BTreeSet<char> = chars
+      .iter()
+      .copied()
+      .filter(|alt| *alt != c && base(*alt) == c)
+      .collect();
+
+    if let Some(config) = self.alternates.get(&c) {
+      alts.extend(config.add.chars().filter(|alt| chars.contains(alt)));
+      alts.retain(|alt| !config.remove.contains(*alt));
+    }
+
+    alts
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Display, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum KerningOrigin {
+  Auto,
+  Manual,
+  Group,
+  Alts,
+}
+
+#[derive(Clone, Copy)]
+pub struct KerningEntry {
+  pub value: i16,
+  pub origin: KerningOrigin,
+  pub limited: bool,
+}
+
+impl KerningEntry {
+  fn new(value: i16, origin: KerningOrigin, limited: bool) -> Self {
+    Self {
+      value,
+      origin,
+      limited,
     }
   }
 }
 
+#[derive(Default, Deserialize, Serialize)]
+pub struct KerningAlternates {
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  add: String,
+  #[serde(default, skip_serializing_if = "String::is_empty")]
+  remove: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct KerningPair {
   #[serde(default = "default_true", skip_serializing_if = "is_true")]
   pub enabled: bool,
-  pub left: char,
-  pub right: char,
+  pub left: KerningSide,
+  pub right: KerningSide,
   pub value: i16,
   #[serde(default = "default_true", skip_serializing_if = "is_true")]
   pub alts: bool,
 }
 
+impl KerningPair {
+  fn group_count(&self) -> usize {
+    usize::from(self.left.glyph().is_none()) + usize::from(self.right.glyph().is_none())
+  }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum KerningSide {
+  Glyph(char),
+  Group(String),
+}
+
+impl KerningSide {
+  fn glyph(&self) -> Option<char> {
+    match self {
+      KerningSide::Glyph(c) => Some(*c),
+      KerningSide::Group(_) => None,
+    }
+  }
+}
+
+fn base(c: char) -> char {
+  let mut base = None;
+  decompose_canonical(c, |d| {
+    base.get_or_insert(d);
+  });
+  base.unwrap_or(c)
+}
+
 fn default_true() -> bool {
   true
 }
@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub struct KerningClasses {
+  left: Vec<Vec<char>>,
+  right: Vec<Vec<char>>,
+  values: Vec<Vec<i16>>,
+  exceptions: BTreeMap<(char, char), i16>,
+}
+
+impl KerningClasses {
+  // groups glyphs that kern the same way into left and right classes,
+  //   seeding them from the named groups, and keeps every pair
+  //   that disagrees with its class value as a glyph exception
+  pub fn new(kerning: &HashMap<(char, char), i16>, groups: &BTreeMap<String, String>) -> Self {
+    let lefts: BTreeSet<char> = kerning.keys().map(|(left, _)| *left).collect();
+    let rights: BTreeSet<char> = kerning.keys().map(|(_, right)| *right).collect();
+
+    let left = KerningClasses::partition(&lefts, groups, |c| {
+      rights
+        .iter()
+        .filter_map(|r| kerning.get(&(c, *r)).map(|v| (*r, *v)))
+        .collect()
+    });
+    let right = KerningClasses::partition(&rights, groups, |c| {
+      lefts
+        .iter()
+        .filter_map(|l| kerning.get(&(*l, c)).map(|v| (*l, *v)))
+        .collect()
+    });
+
+    let mut values = Vec::with_capacity(left.len());
+    let mut exceptions = BTreeMap::new();
+    for left_class in &left {
+      let mut row = Vec::with_capacity(right.len());
+      for right_class in &right {
+        let pairs: Vec<((char, char), i16)> = left_class
+          .iter()
+          .flat_map(|l| right_class.iter().map(move |r| (*l, *r)))
+          .map(|pair| (pair, kerning.get(&pair).copied().unwrap_or(0)))
+          .collect();
+
+        let value = KerningClasses::most_common(pairs.iter().map(|(_, v)| *v));
+        exceptions.extend(pairs.into_iter().filter(|(_, v)| *v != value));
+        row.push(value);
+      }
+      values.push(row);
+    }
+
+    Self {
+      left,
+      right,
+      values,
+      exceptions,
+    }
+  }
+
+  pub fn left(&self) -> &[Vec<char>] {
+    &self.left
+  }
+
+  pub fn right(&self) -> &[Vec<char>] {
+    &self.right
+  }
+
+  pub fn value(&self, left: usize, right: usize) -> i16 {
+    self.values[left][right]
+  }
+
+  pub fn exceptions(&self) -> &BTreeMap<(char, char), i16> {
+    &self.exceptions
+  }
+
+  // named groups claim their members first, the remaining glyphs
+  //   are clustered by identical kerning profiles
+  fn partition(
+    members: &BTreeSet<char>,
+    groups: &BTreeMap<String, String>,
+    profile: impl Fn(char) -> Vec<(char, i16)>,
+  ) -> Vec<Vec<char>> {
+    let mut claimed = BTreeSet::new();
+    let mut classes = Vec::new();
+
+    for group in groups.values() {
+      let class: Vec<char> = group
+        .chars()
+        .collect::<BTreeSet<char>>()
+        .into_iter()
+        .filter(|c| members.contains(c) && claimed.insert(*c))
+        .collect();
+      if !class.is_empty() {
+        classes.push(class);
+      }
+    }
+
+    let mut clusters: BTreeMap<Vec<(char, i16)>, Vec<char>> = BTreeMap::new();
+    for c in members.iter().filter(|c| !claimed.contains(c)) {
+      clusters.entry(profile(*c)).or_default().push(*c);
+    }
+    classes.extend(clusters.into_values());
+
+    classes.sort_by_key(|class| class[0]);
+    classes
+  }
+
+  // ties go to the value closest to zero
+  fn most_common(values: impl Iterator<Item = i16>) -> i16 {
+    let mut counts: BTreeMap<i16, usize> = BTreeMap::new();
+    for value in values {
+      *counts.entry(value).or_default() += 1;
+    }
+
+    counts
+      .into_iter()
+      .max_by_key(|(value, count)| (*count, -i32::from(*value).abs(), *value))
+      .map_or(0, |(value, _)| value)
+  }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::font::{glyphs::Glyph, point::Point, unicode_char::UnicodeChar};
+
+const REFERENCE_CHAR: char = 'H';
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", remote = "Self")]
+pub enum KerningStrategy {
+  // until pixels touch, even diagonally
+  #[default]
+  Touch,
+  // until fewer than `gap` empty pixels are left between the glyphs
+  MinGap {
+    gap: u16,
+  },
+  // as touch, but only between the baseline and the cap height
+  Zone,
+  // until the white space matches the one between two 'H'
+  Area,
+}
+
+// strategies without options can also be written as just their name
+#[derive(Deserialize)]
+#[serde(
+  untagged,
+  expecting = r#"a strategy like "touch" or {"type": "min_gap", "gap": 1}"#
+)]
+enum StrategyForm {
+  Name(StrategyName),
+  Tagged(#[serde(with = "KerningStrategy")] KerningStrategy),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StrategyName {
+  Touch,
+  Zone,
+  Area,
+}
+
+// what the strategies measure against, taken from the 'H' glyph
+pub struct Reference {
+  cap_height: i16,
+  gap: Option<(i64, i64)>,
+}
+
+// leftmost and rightmost pixel of every row
+pub struct Profile {
+  y_min: i16,
+  x_min: i16,
+  rows: Vec<Option<(i16, i16)>>,
+}
+
+impl Serialize for KerningStrategy {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    KerningStrategy::serialize(self, serializer)
+  }
+}
+
+impl<'de> Deserialize<'de> for KerningStrategy {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(match StrategyForm::deserialize(deserializer)? {
+      StrategyForm::Name(StrategyName::Touch) => KerningStrategy::Touch,
+      StrategyForm::Name(StrategyName::Zone) => KerningStrategy::Zone,
+      StrategyForm::Name(StrategyName::Area) => KerningStrategy::Area,
+      StrategyForm::Tagged(strategy) => strategy,
+    })
+  }
+}
+
+impl KerningStrategy {
+  #[allow(clippy::trivially_copy_pass_by_ref)]
+  pub fn is_default(&self) -> bool {
+    *self == KerningStrategy::default()
+  }
+
+  // how many pixels `right` can slide towards `left` from `x_max`,
+  //   or None when the profiles can't tell and pixels must be compared
+  pub fn steps(
+    self,
+    x_max: i32,
+    left: &Profile,
+    right: &Profile,
+    reference: &Reference,
+  ) -> Option<i32> {
+    let touching = closest_offset(left, right, 1, i16::MIN, i16::MAX);
+
+    match self {
+      KerningStrategy::Touch => {
+        // pixels left of the origin can touch past the starting offset
+        if right.x_min < 0 || touching.is_some_and(|offset| offset > x_max) {
+          return None;
+        }
+        Some(steps(x_max, touching))
+      }
+      KerningStrategy::MinGap { gap } => {
+        let offset = closest_offset(left, right, i32::from(gap), i16::MIN, i16::MAX);
+        Some(steps(x_max, offset))
+      }
+      KerningStrategy::Zone => {
+        let offset = closest_offset(left, right, 1, 0, reference.cap_height);
+        Some(steps(x_max, offset))
+      }
+      KerningStrategy::Area => {
+        let touch = steps(x_max, touching);
+        let Some((target, target_rows)) = reference.gap else {
+          return Some(touch);
+        };
+        let Some((gap, rows)) = shared_gap(left, right, x_max) else {
+          return Some(touch);
+        };
+
+        // mean gap minus the reference mean gap, rounded down
+        let excess = (gap * target_rows - target * rows).div_euclid(rows * target_rows);
+        let area = i32::try_from(excess.max(0)).unwrap_or(i32::MAX);
+        Some(area.min(touch))
+      }
+    }
+  }
+}
+
+impl Reference {
+  pub fn new(glyphs: &[Glyph]) -> Self {
+    let reference = glyphs
+      .iter()
+      .find(|g| g.character == UnicodeChar::Char(REFERENCE_CHAR));
+
+    let cap_height = reference
+      .and_then(|g| g.pixels.iter().map(|p| p.y).max())
+      .unwrap_or(i16::MAX);
+    let gap = reference.and_then(|g| {
+      let profile = Profile::new(&g.pixels);
+      shared_gap(&profile, &profile, i32::from(g.bbox.x_max))
+    });
+
+    Self { cap_height, gap }
+  }
+}
+
+#[allow(
+  clippy::cast_possible_truncation,
+  clippy::cast_possible_wrap,
+  clippy::cast_sign_loss
+)]
+impl Profile {
+  pub fn new(pixels: &[Point]) -> Self {
+    let y_min = pixels.iter().map(|p| p.y).min().unwrap_or_default();
+    let y_max = pixels.iter().map(|p| p.y).max().unwrap_or_default();
+    let x_min = pixels.iter().map(|p| p.x).min().unwrap_or_default();
+
+    let mut rows: Vec<Option<(i16, i16)>> = vec![None; (y_max - y_min + 1) as usize];
+    for p in pixels {
+      let row = &mut rows[(p.y - y_min) as usize];
+      *row = Some(match row {
+        Some((leftmost, rightmost)) => ((*leftmost).min(p.x), (*rightmost).max(p.x)),
+        None => (p.x, p.x),
+      });
+    }
+
+    Self { y_min, x_min, rows }
+  }
+
+  fn rightmost(&self) -> impl Iterator<Item = (i16, i16)> + '_ {
+    self
+      .rows
+      .iter()
+      .enumerate()
+      .filter_map(|(i, row)| row.map(|(_, rightmost)| (self.y_min + i as i16, rightmost)))
+  }
+
+  fn leftmost(&self, y: i16) -> Option<i16> {
+    let i = usize::try_from(y - self.y_min).ok()?;
+    self
+      .rows
+      .get(i)
+      .copied()
+      .flatten()
+      .map(|(leftmost, _)| leftmost)
+  }
+}
+
+// the largest offset at which `right` comes within `distance` pixels of `left`,
+//   from the rightmost pixel of each left row against the leftmost pixel
+//   of the right rows next to it
+fn closest_offset(
+  left: &Profile,
+  right: &Profile,
+  distance: i32,
+  y_min: i16,
+  y_max: i16,
+) -> Option<i32> {
+  left
+    .rightmost()
+    .filter(|(y, _)| (y_min..=y_max).contains(y))
+    .filter_map(|(y, rightmost)| {
+      (y.saturating_sub(1)..=y.saturating_add(1))
+        .filter(|y| (y_min..=y_max).contains(y))
+        .filter_map(|y| right.leftmost(y))
+        .map(|leftmost| i32::from(rightmost) - i32::from(leftmost) + distance)
+        .max()
+    })
+    .max()
+}
+
+// offsets tried from `x_max` down to 0 before reaching `offset`
+fn steps(x_max: i32, offset: Option<i32>) -> i32 {
+  match offset {
+    Some(offset) => (x_max - offset).clamp(0, x_max + 1),
+    None => x_max + 1,
+  }
+}
+
+// total empty pixels between the glyphs over the rows both have ink in,
+//   with `right` placed at `x_max`, and the number of those rows
+fn shared_gap(left: &Profile, right: &Profile, x_max: i32) -> Option<(i64, i64)> {
+  let (gap, rows) = left
+    .rightmost()
+    .filter_map(|(y, rightmost)| {
+      let leftmost = right.leftmost(y)?;
+      Some(i64::from(x_max) + i64::from(leftmost) - i64::from(rightmost) - 1)
+    })
+    .fold((0, 0), |(gap, rows), row| (gap + row, rows + 1));
+
+  (rows > 0).then_some((gap, rows))
+}
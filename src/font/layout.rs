@@ -0,0 +1,118 @@
+use std::num::NonZeroU16;
+
+use serde::{Deserialize, Serialize};
+
+use crate::font::sheet::colors;
+
+const COLUMNS: u16 = 26;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Layout {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  columns: Option<NonZeroU16>,
+  // empty pixels between neighbouring tiles
+  #[serde(default, skip_serializing_if = "is_zero")]
+  gutter: u16,
+  // empty pixels around the edge of the sheet
+  #[serde(default, skip_serializing_if = "is_zero")]
+  padding: u16,
+  #[serde(default, skip_serializing_if = "Palette::is_default")]
+  palette: Palette,
+}
+
+// the checkerboard the glyphs are drawn on
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+  sheet_light: [u8; 3],
+  sheet_dark: [u8; 3],
+  baseline_light: [u8; 3],
+  baseline_dark: [u8; 3],
+  inactive_sheet_light: [u8; 3],
+  inactive_sheet_dark: [u8; 3],
+  inactive_baseline_light: [u8; 3],
+  inactive_baseline_dark: [u8; 3],
+  gutter: [u8; 3],
+}
+
+impl Layout {
+  pub fn columns(&self) -> u32 {
+    u32::from(self.columns.map_or(COLUMNS, NonZeroU16::get))
+  }
+
+  pub fn gutter(&self) -> u32 {
+    u32::from(self.gutter)
+  }
+
+  pub fn padding(&self) -> u32 {
+    u32::from(self.padding)
+  }
+
+  pub fn palette(&self) -> &Palette {
+    &self.palette
+  }
+
+  pub fn is_default(&self) -> bool {
+    self.columns.is_none() && self.gutter == 0 && self.padding == 0 && self.palette.is_default()
+  }
+}
+
+impl Palette {
+  #[allow(clippy::fn_params_excessive_bools)]
+  pub fn tile(&self, light: bool, baseline: bool, inactive: bool) -> [u8; 3] {
+    match (light, baseline, inactive) {
+      (true, false, false) => self.sheet_light,
+      (false, false, false) => self.sheet_dark,
+      (true, true, false) => self.baseline_light,
+      (false, true, false) => self.baseline_dark,
+      (true, false, true) => self.inactive_sheet_light,
+      (false, false, true) => self.inactive_sheet_dark,
+      (true, true, true) => self.inactive_baseline_light,
+      (false, true, true) => self.inactive_baseline_dark,
+    }
+  }
+
+  pub fn gutter(&self) -> [u8; 3] {
+    self.gutter
+  }
+
+  // never ink, whatever the ink rule says; the gutter is left out
+  //   since it is never read and white may well be ink
+  pub fn background(&self) -> [[u8; 3]; 8] {
+    [
+      self.sheet_light,
+      self.sheet_dark,
+      self.baseline_light,
+      self.baseline_dark,
+      self.inactive_sheet_light,
+      self.inactive_sheet_dark,
+      self.inactive_baseline_light,
+      self.inactive_baseline_dark,
+    ]
+  }
+
+  pub fn is_default(&self) -> bool {
+    *self == Palette::default()
+  }
+}
+
+impl Default for Palette {
+  fn default() -> Self {
+    Self {
+      sheet_light: colors::SHEET_LIGHT,
+      sheet_dark: colors::SHEET_DARK,
+      baseline_light: colors::BASELINE_LIGHT,
+      baseline_dark: colors::BASELINE_DARK,
+      inactive_sheet_light: colors::INACTIVE_SHEET_LIGHT,
+      inactive_sheet_dark: colors::INACTIVE_SHEET_DARK,
+      inactive_baseline_light: colors::INACTIVE_BASELINE_LIGHT,
+      inactive_baseline_dark: colors::INACTIVE_BASELINE_DARK,
+      gutter: colors::GUTTER,
+    }
+  }
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_zero(v: &u16) -> bool {
+  *v == 0
+}
@@ -0,0 +1,58 @@
+use std::num::NonZeroU16;
+
+use read_fonts::types::Tag;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+pub struct Ligature {
+  sequence: String,
+  #[serde(default = "default_width", skip_serializing_if = "is_default_width")]
+  width: NonZeroU16,
+  #[serde(default, skip_serializing_if = "LigatureFeature::is_default")]
+  feature: LigatureFeature,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LigatureFeature {
+  #[default]
+  Liga,
+  Dlig,
+}
+
+impl Ligature {
+  pub fn sequence(&self) -> &str {
+    &self.sequence
+  }
+
+  pub fn width(&self) -> u16 {
+    self.width.get()
+  }
+
+  pub fn feature(&self) -> LigatureFeature {
+    self.feature
+  }
+}
+
+impl LigatureFeature {
+  pub fn tag(self) -> Tag {
+    match self {
+      LigatureFeature::Liga => Tag::new(b"liga"),
+      LigatureFeature::Dlig => Tag::new(b"dlig"),
+    }
+  }
+
+  #[allow(clippy::trivially_copy_pass_by_ref)]
+  fn is_default(&self) -> bool {
+    *self == LigatureFeature::default()
+  }
+}
+
+fn default_width() -> NonZeroU16 {
+  NonZeroU16::MIN
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_width(v: &NonZeroU16) -> bool {
+  *v == NonZeroU16::MIN
+}
@@ -1,12 +1,23 @@
+pub mod alternate;
 mod builder;
+pub mod charset;
+pub mod composite;
 pub mod config;
+pub mod connectivity;
 mod contour;
 pub mod dimensions;
+pub mod forms;
 mod glyphs;
+pub mod ink;
 pub mod kerning;
+mod kerning_classes;
+mod kerning_strategy;
+pub mod layout;
+pub mod ligature;
 pub mod metadata;
 mod point;
 pub mod project;
 mod sheet;
 pub mod subfamily;
+pub mod terminal;
 pub mod unicode_char;
@@ -1,7 +1,5 @@
 use std::fmt::{Debug, Display};
 
-pub type Edge = (Point, Point);
-
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Point {
   pub x: i16,
@@ -18,31 +16,6 @@ impl Point {
     self.y *= scale;
   }
 
-  pub fn above(self) -> Self {
-    Point::new(self.x, self.y - 1)
-  }
-
-  pub fn below(self) -> Self {
-    Point::new(self.x, self.y + 1)
-  }
-
-  pub fn left(self) -> Self {
-    Point::new(self.x - 1, self.y)
-  }
-
-  pub fn right(self) -> Self {
-    Point::new(self.x + 1, self.y)
-  }
-
-  pub fn adjacent(self) -> Vec<Point> {
-    vec![
-      Point::new(self.x - 1, self.y),
-      Point::new(self.x + 1, self.y),
-      Point::new(self.x, self.y - 1),
-      Point::new(self.x, self.y + 1),
-    ]
-  }
-
   pub fn surrounding(self) -> [Self; 8] {
     [
       Point::new(self.x - 1, self.y - 1),
@@ -55,28 +28,6 @@ impl Point {
       Point::new(self.x + 1, self.y + 1),
     ]
   }
-
-  pub fn top_edge(self) -> Edge {
-    (self, Point::new(self.x + 1, self.y))
-  }
-
-  pub fn right_edge(self) -> Edge {
-    (
-      Point::new(self.x + 1, self.y),
-      Point::new(self.x + 1, self.y + 1),
-    )
-  }
-
-  pub fn bottom_edge(self) -> Edge {
-    (
-      Point::new(self.x + 1, self.y + 1),
-      Point::new(self.x, self.y + 1),
-    )
-  }
-
-  pub fn left_edge(self) -> Edge {
-    (Point::new(self.x, self.y + 1), self)
-  }
 }
 
 impl Display for Point {
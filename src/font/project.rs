@@ -1,18 +1,30 @@
 use std::{
+  collections::{HashMap, HashSet},
   fs::{self, File},
   io::Write,
   path::{Path, PathBuf},
 };
 
-use anyhow::Result;
+use anyhow::{Result, ensure};
+use rayon::prelude::*;
+use read_fonts::types::Tag;
 use serde::Serialize;
 
 use crate::font::{
-  builder::Builder, config::Config, contour::contour, glyphs::Glyph, sheet::Sheet,
+  builder::Builder,
+  composite::Composites,
+  config::Config,
+  contour::{GlyphError, contour},
+  glyphs::{C2SC_TAG, CASE_TAG, Glyph, Layer, SMCP_TAG},
+  kerning::KerningEntry,
+  point::Point,
+  sheet::{Sheet, Tile},
+  unicode_char::UnicodeChar,
 };
 
 const CONFIG_JSON: &str = "config.json";
 const SHEET_PNG: &str = "sheet.png";
+const OLD_SHEET_PNG: &str = "sheet.old.png";
 const SAMPLES: &str = "samples";
 
 pub struct Project {
@@ -57,8 +69,9 @@ impl Project {
 
   pub fn build(&self) -> Result<()> {
     let glyphs = self.read_glyphs()?;
+    Project::verify(&glyphs)?;
 
-    let kerning = self.config.kerning().kern(&glyphs);
+    let kerning = self.config.kerning().kern(&glyphs, self.config.charset())?;
 
     let mut builder = Builder::new(glyphs, kerning);
     let bytes = builder.build(&self.config)?;
@@ -69,30 +82,458 @@ impl Project {
     Ok(())
   }
 
+  // lays the sheet out again for the current config, reading it with
+  //   the config it was drawn for and keeping the old one beside it
+  pub fn migrate_sheet(&self, old_config: &Config) -> Result<PathBuf> {
+    let path = self.sheet.path();
+    let old_path = path.with_file_name(OLD_SHEET_PNG);
+    ensure!(
+      !old_path.exists(),
+      "{} already exists, move it out of the way first",
+      old_path.display()
+    );
+
+    let image = self.sheet.migrate(old_config, &self.config)?;
+    fs::copy(path, &old_path)?;
+    image.save(path)?;
+
+    Ok(old_path)
+  }
+
+  pub fn kerning(&self) -> Result<HashMap<(char, char), KerningEntry>> {
+    let glyphs = self.read_glyphs()?;
+    self
+      .config
+      .kerning()
+      .kern_entries(&glyphs, self.config.charset())
+  }
+
+  // every outline must fill exactly the pixels it was traced from
+  fn verify(glyphs: &[Glyph]) -> Result<()> {
+    let mismatches: Vec<String> = glyphs
+      .par_iter()
+      .filter_map(|glyph| {
+        let mut diffs: Vec<String> = glyph
+          .outline_diff(1)
+          .map(|diff| format!("'{}' (+ added, - lost):\n{diff}", glyph.character))
+          .into_iter()
+          .collect();
+        diffs.extend(glyph.layers.iter().filter_map(|layer| {
+          let diff = layer.outline_diff()?;
+          let [r, g, b, a] = layer.color;
+          Some(format!(
+            "'{}' layer #{r:02x}{g:02x}{b:02x}{a:02x} (+ added, - lost):\n{diff}",
+            glyph.character
+          ))
+        }));
+        (!diffs.is_empty()).then(|| diffs.join("\n"))
+      })
+      .collect();
+
+    ensure!(
+      mismatches.is_empty(),
+      "Outlines of {} glyphs do not match their pixels:\n\n{}",
+      mismatches.len(),
+      mismatches.join("\n")
+    );
+
+    Ok(())
+  }
+
   fn read_glyphs(&self) -> Result<Vec<Glyph>> {
-    let glyph_pixels = self.sheet.read(&self.config)?;
-    let glyph_chars = Glyph::glyphs();
-    let mut glyphs: Vec<Glyph> = glyph_pixels
-      .into_iter()
+    let tiles = self.sheet.read(&self.config)?;
+    let glyph_chars = Glyph::glyphs(self.config.charset());
+    let glyphs: Vec<Option<Glyph>> = tiles
+      .into_par_iter()
       .enumerate()
-      .filter_map(|(i, pixels)| {
-        let character = *glyph_chars.get(i)?;
+      .map(|(i, tile)| {
+        let Some(character) = glyph_chars.get(i).copied() else {
+          return Ok(None);
+        };
 
         if character.is_space() {
-          return Some(Glyph::new(character, Vec::new(), Vec::new()));
+          return Ok(None);
         }
 
-        let contour = contour(&pixels);
-        if contour.is_empty() {
+        self.tile_glyph(character, tile)
+      })
+      .collect::<Result<_>>()?;
+    let mut glyphs: Vec<Glyph> = glyphs.into_iter().flatten().collect();
+    glyphs.extend(
+      glyph_chars
+        .iter()
+        .filter(|c| c.is_space())
+        .map(|c| Glyph::new(*c, Vec::new(), Vec::new())),
+    );
+
+    if self.config.composites().enabled() {
+      let composites = self.composite_glyphs(&glyphs, &glyph_chars)?;
+      glyphs.extend(composites);
+    }
+
+    let generated = self.terminal_glyphs(&glyphs)?;
+    glyphs.extend(generated);
+
+    if self.config.vertical() {
+      let alternates = self.vertical_glyphs(&glyphs)?;
+      glyphs.extend(alternates);
+    }
+
+    let ligatures = self.ligature_glyphs()?;
+    glyphs.extend(ligatures);
+
+    let alternates = self.alternate_glyphs()?;
+    glyphs.extend(alternates);
+
+    let marks = self.mark_glyphs()?;
+    glyphs.extend(marks);
+
+    let forms = self.form_glyphs(&glyphs)?;
+    glyphs.extend(forms);
+
+    self.report_ligature_components(&glyphs);
+    self.report_alternate_bases(&glyphs);
+
+    glyphs.sort();
+
+    Ok(glyphs)
+  }
+
+  // accented letters left empty in the sheet, built from their base and accent
+  fn composite_glyphs(&self, glyphs: &[Glyph], glyph_chars: &[UnicodeChar]) -> Result<Vec<Glyph>> {
+    let composites = self.config.composites();
+    let drawn: HashMap<char, &[Point]> = glyphs
+      .iter()
+      .filter_map(|g| Some((g.character.try_into().ok()?, g.pixels.as_slice())))
+      .collect();
+
+    let (composed, misfits): (Vec<_>, Vec<_>) = glyph_chars
+      .iter()
+      .filter_map(|character| {
+        let c = char::try_from(*character).ok()?;
+        if drawn.contains_key(&c) {
           return None;
         }
+        Some((*character, c, composites.compose(c, &drawn)?))
+      })
+      .partition(|(_, _, pixels)| Composites::fits(pixels, self.config.dimensions()));
+
+    if !misfits.is_empty() {
+      let misfits: String = misfits.iter().map(|(_, c, _)| c).collect();
+      eprintln!(
+        "Warning: composed \"{misfits}\" do not fit their tiles and were left out, draw them or change the gaps"
+      );
+    }
+
+    let composites: Vec<Option<Glyph>> = composed
+      .into_par_iter()
+      .map(|(character, _, pixels)| {
+        let contour = self.trace(character, &pixels, None)?;
+        Ok((!contour.is_empty()).then(|| Glyph::new(character, contour, pixels)))
+      })
+      .collect::<Result<_>>()?;
+
+    Ok(composites.into_iter().flatten().collect())
+  }
+
+  // box drawing and friends left empty in the sheet, generated for the tile
+  fn terminal_glyphs(&self, glyphs: &[Glyph]) -> Result<Vec<Glyph>> {
+    let drawn: HashSet<UnicodeChar> = glyphs.iter().map(|g| g.character).collect();
+
+    let generated: Vec<Option<Glyph>> = self
+      .config
+      .terminal()
+      .glyphs(self.config.dimensions())
+      .into_par_iter()
+      .map(|(c, pixels)| {
+        let character = UnicodeChar::Char(c);
+        if drawn.contains(&character) {
+          return Ok(None);
+        }
+
+        // blank braille has no outline but is still a glyph
+        let contour = self.trace(character, &pixels, None)?;
+        Ok(
+          (pixels.is_empty() || !contour.is_empty())
+            .then(|| Glyph::new(character, contour, pixels)),
+        )
+      })
+      .collect::<Result<_>>()?;
+
+    Ok(generated.into_iter().flatten().collect())
+  }
+
+  fn vertical_glyphs(&self, glyphs: &[Glyph]) -> Result<Vec<Glyph>> {
+    let ascender: i16 = self.config.dimensions().ascender_height().try_into()?;
+    let descender: i16 = self.config.dimensions().descender_height().try_into()?;
+
+    let alternates: Vec<Option<Glyph>> = glyphs
+      .par_iter()
+      .map(|glyph| {
+        let Some(character) = glyph.vertical_alternate() else {
+          return Ok(None);
+        };
+        let pixels = glyph.rotated_pixels(ascender, descender);
+        let contour = self.trace(character, &pixels, None)?;
+        Ok((!contour.is_empty()).then(|| Glyph::new(character, contour, pixels)))
+      })
+      .collect::<Result<_>>()?;
+
+    Ok(alternates.into_iter().flatten().collect())
+  }
+
+  fn ligature_glyphs(&self) -> Result<Vec<Glyph>> {
+    for ligature in self.config.ligatures() {
+      ensure!(
+        ligature.sequence().chars().count() >= 2,
+        "Ligature \"{}\" needs at least two characters",
+        ligature.sequence()
+      );
+    }
+
+    let tiles = self.sheet.read_ligatures(&self.config)?;
+    let ligatures: Vec<Option<Glyph>> = tiles
+      .into_par_iter()
+      .enumerate()
+      .map(|(i, tile)| {
+        let character = UnicodeChar::Ligature(i.try_into()?);
+        self.tile_glyph(character, tile)
+      })
+      .collect::<Result<_>>()?;
+
+    for (ligature, glyph) in self.config.ligatures().iter().zip(&ligatures) {
+      if glyph.is_none() {
+        eprintln!(
+          "Warning: ligature \"{}\" has an empty tile and was left out",
+          ligature.sequence()
+        );
+      }
+    }
+
+    Ok(ligatures.into_iter().flatten().collect())
+  }
+
+  // a ligature only applies when every character of its sequence is drawn
+  fn report_ligature_components(&self, glyphs: &[Glyph]) {
+    let characters: HashSet<UnicodeChar> = glyphs.iter().map(|g| g.character).collect();
+
+    for (i, ligature) in self.config.ligatures().iter().enumerate() {
+      let drawn = u16::try_from(i).is_ok_and(|i| characters.contains(&UnicodeChar::Ligature(i)));
+      let undrawn = ligature
+        .sequence()
+        .chars()
+        .find(|c| !characters.contains(&UnicodeChar::Char(*c)));
+      if let (true, Some(c)) = (drawn, undrawn) {
+        eprintln!(
+          "Warning: ligature \"{}\" was left out since '{c}' is not drawn",
+          ligature.sequence()
+        );
+      }
+    }
+  }
+
+  fn alternate_glyphs(&self) -> Result<Vec<Glyph>> {
+    let tiles = self.sheet.read_alternates(&self.config)?;
+
+    let mut ordinals: HashMap<(char, Tag), u16> = HashMap::new();
+    let mut alternates = Vec::new();
+    for (alternate, tile) in self.config.alternates().iter().zip(tiles) {
+      let c = alternate.character();
+      let tag = alternate.tag()?;
+
+      let ordinal = ordinals.entry((c, tag)).or_default();
+      let character = UnicodeChar::Alternate(c, tag, *ordinal);
+
+      let Some(glyph) = self.tile_glyph(character, tile)? else {
+        eprintln!(
+          "Warning: the \"{}\" alternate of '{c}' has an empty tile and was left out",
+          alternate.feature()
+        );
+        continue;
+      };
+      *ordinal += 1;
 
-        Some(Glyph::new(character, contour, pixels))
+      alternates.push(glyph);
+    }
+
+    Ok(alternates)
+  }
+
+  // an alternate only applies when the character it replaces is drawn
+  fn report_alternate_bases(&self, glyphs: &[Glyph]) {
+    let characters: HashSet<UnicodeChar> = glyphs.iter().map(|g| g.character).collect();
+    let alternates: HashSet<(char, Tag)> = glyphs
+      .iter()
+      .filter_map(|g| match g.character {
+        UnicodeChar::Alternate(c, tag, _) => Some((c, tag)),
+        _ => None,
       })
       .collect();
-    glyphs.sort();
 
-    Ok(glyphs)
+    let mut reported = HashSet::new();
+    for alternate in self.config.alternates() {
+      let c = alternate.character();
+      let Ok(tag) = alternate.tag() else {
+        continue;
+      };
+      if alternates.contains(&(c, tag))
+        && !characters.contains(&UnicodeChar::Char(c))
+        && reported.insert((c, tag))
+      {
+        eprintln!(
+          "Warning: the \"{}\" alternate of '{c}' was left out since '{c}' is not drawn",
+          alternate.feature()
+        );
+      }
+    }
+  }
+
+  fn mark_glyphs(&self) -> Result<Vec<Glyph>> {
+    for mark in self.config.marks() {
+      ensure!(
+        UnicodeChar::Char(*mark).mark_class().is_some(),
+        "U+{:04X} is not a combining mark",
+        u32::from(*mark)
+      );
+    }
+
+    let tiles = self.sheet.read_marks(&self.config)?;
+    let marks: Vec<Option<Glyph>> = self
+      .config
+      .marks()
+      .par_iter()
+      .zip(tiles)
+      .map(|(mark, tile)| {
+        let character = UnicodeChar::Char(*mark);
+        self.tile_glyph(character, tile)
+      })
+      .collect::<Result<_>>()?;
+
+    Ok(marks.into_iter().flatten().collect())
+  }
+
+  // small caps and case forms not already drawn as alternates
+  fn form_glyphs(&self, glyphs: &[Glyph]) -> Result<Vec<Glyph>> {
+    let forms = self.config.forms();
+    let mut existing: HashSet<UnicodeChar> = glyphs.iter().map(|g| g.character).collect();
+    let find = |c: char| glyphs.iter().find(|g| g.character == UnicodeChar::Char(c));
+
+    let mut derived = Vec::new();
+
+    let x_height = find('x').and_then(Glyph::height);
+    let cap_height = find('H').and_then(Glyph::height);
+    if forms.small_caps()
+      && let (Some(x_height), Some(cap_height)) = (x_height, cap_height)
+    {
+      for glyph in glyphs {
+        let UnicodeChar::Char(lower) = glyph.character else {
+          continue;
+        };
+        let mut upper = lower.to_uppercase();
+        let (Some(upper), None) = (upper.next(), upper.next()) else {
+          continue;
+        };
+        if upper == lower {
+          continue;
+        }
+        let Some(upper_glyph) = find(upper) else {
+          continue;
+        };
+
+        let pixels = upper_glyph.scaled_pixels(x_height, cap_height);
+        let contour = self.trace(UnicodeChar::Alternate(lower, SMCP_TAG, 0), &pixels, None)?;
+        if contour.is_empty() {
+          continue;
+        }
+
+        for character in [
+          UnicodeChar::Alternate(lower, SMCP_TAG, 0),
+          UnicodeChar::Alternate(upper, C2SC_TAG, 0),
+        ] {
+          if existing.insert(character) {
+            derived.push(Glyph::new(character, contour.clone(), pixels.clone()));
+          }
+        }
+      }
+    }
+
+    let case_shift: i16 = forms.case_shift().try_into()?;
+    if case_shift != 0 {
+      for c in forms.case_characters().chars() {
+        let character = UnicodeChar::Alternate(c, CASE_TAG, 0);
+        let Some(glyph) = find(c) else {
+          continue;
+        };
+        if !existing.insert(character) {
+          continue;
+        }
+
+        let pixels = glyph.shifted_pixels(case_shift);
+        let contour = self.trace(character, &pixels, None)?;
+        if !contour.is_empty() {
+          derived.push(Glyph::new(character, contour, pixels));
+        }
+      }
+    }
+
+    Ok(derived)
+  }
+
+  // the glyph drawn in a tile, with a layer for every colour in it
+  //   unless it is drawn in the glyph colour alone
+  fn tile_glyph(&self, character: UnicodeChar, mut tile: Tile) -> Result<Option<Glyph>> {
+    let contour = self.trace(character, &tile.pixels, Some(&tile))?;
+    if contour.is_empty() {
+      return Ok(None);
+    }
+
+    let mut layers = Vec::new();
+    for (color, pixels) in std::mem::take(&mut tile.layers) {
+      let contours = self.trace(character, &pixels, Some(&tile))?;
+      if !contours.is_empty() {
+        layers.push(Layer {
+          color,
+          contours,
+          pixels,
+        });
+      }
+    }
+    if layers.iter().all(Layer::is_foreground) {
+      layers.clear();
+    }
+
+    Ok(Some(
+      Glyph::new(character, contour, tile.pixels)
+        .with_anchors(tile.anchors)
+        .with_layers(layers),
+    ))
+  }
+
+  // outlines the pixels, or leaves them empty with a warning
+  //   when the outline is invalid and such glyphs are skipped
+  fn trace(
+    &self,
+    character: UnicodeChar,
+    pixels: &[Point],
+    tile: Option<&Tile>,
+  ) -> Result<Vec<Vec<Point>>> {
+    let error = match contour(pixels, self.config.connectivity()) {
+      Ok(contour) => return Ok(contour),
+      Err(error) => error,
+    };
+
+    let ascender = i32::from(self.config.dimensions().ascender_height());
+    let point = error.point();
+    let error = GlyphError {
+      character,
+      tile: tile.map(|tile| (tile.column, tile.row)),
+      pixel: (i32::from(point.x), ascender - i32::from(point.y)),
+      error,
+    };
+
+    ensure!(self.config.skip_invalid_glyphs(), error);
+    eprintln!("Warning: {error}, skipping");
+    Ok(Vec::new())
   }
 
   pub fn font_path(&self) -> PathBuf {
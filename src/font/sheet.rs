@@ -1,11 +1,14 @@
-use std::path::PathBuf;
+use std::{
+  collections::{BTreeMap, HashMap, HashSet},
+  path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use image::{ImageReader, Rgb, RgbImage};
+use anyhow::{Result, ensure};
+use image::{DynamicImage, ImageReader, Rgb, RgbImage, Rgba, RgbaImage};
 
-use crate::font::{config::Config, glyphs::sheet_unicode_blocks, point::Point};
+use crate::font::{config::Config, ink::Ink, point::Point, unicode_char::UnicodeChar};
 
-mod colors {
+pub mod colors {
   pub const SHEET_LIGHT: [u8; 3] = [210, 107, 152]; // #D26B98
   pub const SHEET_DARK: [u8; 3] = [173, 58, 108]; // #AD3A6C
   pub const BASELINE_LIGHT: [u8; 3] = [208, 71, 138]; // #D0478A
@@ -17,110 +20,442 @@ mod colors {
   pub const INACTIVE_BASELINE_DARK: [u8; 3] = [125, 77, 48]; // #7D4D30
 
   pub const GLYPH_COLOR: [u8; 3] = [0, 0, 0]; // #000000
-}
+  pub const ANCHOR_COLOR: [u8; 3] = [0, 0, 255]; // #0000FF
 
-const GLYPHS_MAX: u32 = sheet_unicode_blocks::BASIC_LATIN_SUPPORTED
-  + sheet_unicode_blocks::LATIN_SUPPLEMENT_SUPPORTED
-  + sheet_unicode_blocks::LATIN_A_SUPPORTED
-  + sheet_unicode_blocks::CURRENCY_SUPPORTED
-  + sheet_unicode_blocks::NOT_DEF;
-const COLUMNS: u32 = 26;
-const ROWS: u32 = GLYPHS_MAX.div_ceil(COLUMNS);
+  pub const GUTTER: [u8; 3] = [255, 255, 255]; // #FFFFFF
+}
 
 pub struct Sheet {
   path: PathBuf,
 }
 
+#[derive(Default)]
+pub struct Tile {
+  pub column: u32,
+  pub row: u32,
+  pub pixels: Vec<Point>,
+  pub anchors: Vec<Point>,
+  // the pixels of every colour, when reading colour glyphs
+  pub layers: BTreeMap<[u8; 4], Vec<Point>>,
+  // column and row from the top left of the tile
+  pub near_ink: Vec<(u32, u32)>,
+}
+
 impl Sheet {
   pub fn new(path: PathBuf) -> Self {
     Self { path }
   }
 
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
   pub fn create(config: &Config) -> RgbImage {
     let dimensions = config.dimensions();
-    let block_width = u32::from(dimensions.tile_width());
-    let width = block_width * COLUMNS;
+    let layout = config.layout();
+    let palette = layout.palette();
+    let columns = layout.columns();
+    let glyphs_max = Sheet::glyphs_max(config);
+    let glyph_rows = glyphs_max.div_ceil(columns);
 
     let ascender_height = u32::from(dimensions.ascender_height());
-    let descender_height = u32::from(dimensions.descender_height());
-    let block_height = u32::from(dimensions.tile_height());
+    let has_baseline = dimensions.descender_height() != 0;
+    let tile_height = u32::from(dimensions.tile_height());
 
-    let height = block_height * ROWS;
+    let extra_tiles = Sheet::extra_tiles(config);
+    let rows = extra_tiles.last().map_or(glyph_rows, |(_, row, _)| row + 1);
+    let (width, height) = Sheet::size(config);
 
-    let mut image = RgbImage::new(width, height);
+    let mut image = RgbImage::from_pixel(width, height, Rgb(palette.gutter()));
 
-    for i in 0..width {
-      for j in 0..height {
-        let column = i / block_width;
-        let row = j / block_height;
-        let is_width_light = column % 2 == 0;
-        let is_height_flipped = row % 2 == 1;
-        let is_baseline = descender_height != 0 && j % block_height == ascender_height - 1;
-        let is_inactive = (column + (row * COLUMNS)) >= GLYPHS_MAX;
-        let color = if is_width_light == is_height_flipped {
-          if is_baseline {
-            if is_inactive {
-              colors::INACTIVE_BASELINE_DARK
-            } else {
-              colors::BASELINE_DARK
-            }
-          } else if is_inactive {
-            colors::INACTIVE_SHEET_DARK
-          } else {
-            colors::SHEET_DARK
-          }
-        } else if is_baseline {
-          if is_inactive {
-            colors::INACTIVE_BASELINE_LIGHT
-          } else {
-            colors::BASELINE_LIGHT
-          }
-        } else if is_inactive {
-          colors::INACTIVE_SHEET_LIGHT
+    for row in 0..rows {
+      for column in 0..columns {
+        let (is_width_light, is_inactive, span) = if row < glyph_rows {
+          (column % 2 == 0, (column + (row * columns)) >= glyphs_max, 1)
+        } else if let Some(index) = extra_tiles
+          .iter()
+          .position(|(c, r, _)| *r == row && *c == column)
+        {
+          (index % 2 == 0, false, extra_tiles[index].2)
+        } else if extra_tiles
+          .iter()
+          .any(|(c, r, w)| *r == row && (*c..c + w).contains(&column))
+        {
+          continue;
         } else {
-          colors::SHEET_LIGHT
+          (column % 2 == 0, true, 1)
         };
-        let pixel = Rgb(color);
-        image.put_pixel(i, j, pixel);
+        let is_height_flipped = row % 2 == 1;
+
+        let (x_start, y_start) = Sheet::tile_origin(config, column, row);
+        let width = Sheet::span_width(config, span);
+        for j in 0..tile_height {
+          let is_baseline = has_baseline && j == ascender_height - 1;
+          let color = palette.tile(
+            is_width_light != is_height_flipped,
+            is_baseline,
+            is_inactive,
+          );
+          for i in 0..width {
+            image.put_pixel(x_start + i, y_start + j, Rgb(color));
+          }
+        }
       }
     }
 
     image
   }
 
-  pub fn read(&self, config: &Config) -> Result<Vec<Vec<Point>>> {
-    let image = ImageReader::open(&self.path)?.decode()?;
-    let image = image.to_rgb8();
+  pub fn read(&self, config: &Config) -> Result<Vec<Tile>> {
+    let image = self.open(config)?;
 
     let tile_width = u32::from(config.dimensions().tile_width());
-    let tile_height = u32::from(config.dimensions().tile_height());
+    let columns = config.layout().columns();
+
+    let mut tiles = Vec::new();
+    for j in 0..Sheet::glyphs_max(config).div_ceil(columns) {
+      for i in 0..columns {
+        let (x_start, y_start) = Sheet::tile_origin(config, i, j);
+        let tile = Sheet::read_tile(&image, config, x_start, y_start, tile_width)?;
+        tiles.push(Tile {
+          column: i,
+          row: j,
+          ..tile
+        });
+      }
+    }
+
+    Sheet::report_near_ink(&tiles);
+    Ok(tiles)
+  }
+
+  pub fn read_ligatures(&self, config: &Config) -> Result<Vec<Tile>> {
+    let tiles = Sheet::extra_tiles(config)
+      .into_iter()
+      .take(config.ligatures().len());
+    self.read_extra_tiles(config, tiles)
+  }
+
+  pub fn read_alternates(&self, config: &Config) -> Result<Vec<Tile>> {
+    let tiles = Sheet::extra_tiles(config)
+      .into_iter()
+      .skip(config.ligatures().len())
+      .take(config.alternates().len());
+    self.read_extra_tiles(config, tiles)
+  }
+
+  pub fn read_marks(&self, config: &Config) -> Result<Vec<Tile>> {
+    let tiles = Sheet::extra_tiles(config)
+      .into_iter()
+      .skip(config.ligatures().len() + config.alternates().len());
+    self.read_extra_tiles(config, tiles)
+  }
+
+  fn read_extra_tiles(
+    &self,
+    config: &Config,
+    tiles: impl Iterator<Item = (u32, u32, u32)>,
+  ) -> Result<Vec<Tile>> {
+    let image = self.open(config)?;
+
+    let tiles = tiles
+      .map(|(column, row, width)| {
+        let (x_start, y_start) = Sheet::tile_origin(config, column, row);
+        let width = Sheet::span_width(config, width);
+        let tile = Sheet::read_tile(&image, config, x_start, y_start, width)?;
+        Ok(Tile {
+          column,
+          row,
+          ..tile
+        })
+      })
+      .collect::<Result<Vec<_>>>()?;
+
+    Sheet::report_near_ink(&tiles);
+    Ok(tiles)
+  }
+
+  // the sheet must be laid out for `config`, any other sheet would
+  //   be read as garbage
+  fn open(&self, config: &Config) -> Result<RgbaImage> {
+    let image = ImageReader::open(&self.path)?.decode()?.to_rgba8();
+
+    let (width, height) = Sheet::size(config);
+    ensure!(
+      image.dimensions() == (width, height),
+      "{} is {}x{} but config.json lays out a {width}x{height} sheet, \
+       run `foundry sheet migrate old-config.json` with the config it was drawn for",
+      self.path.display(),
+      image.width(),
+      image.height(),
+    );
+
+    Ok(image)
+  }
+
+  // redraws a sheet laid out for `old` on a blank sheet for `new`,
+  //   every tile moved to wherever its glyph now sits, on the baseline
+  pub fn migrate(&self, old: &Config, new: &Config) -> Result<RgbaImage> {
+    let image = ImageReader::open(&self.path)?.decode()?;
+    let image = image.to_rgba8();
+    let mut migrated = DynamicImage::ImageRgb8(Sheet::create(new)).to_rgba8();
+
+    let old_tiles: HashMap<String, (u32, u32, u32)> = Sheet::labelled_tiles(old)
+      .into_iter()
+      .map(|(label, column, row, width)| (label, (column, row, width)))
+      .collect();
+    let new_tiles = Sheet::labelled_tiles(new);
+
+    let height = u32::from(new.dimensions().tile_height());
+    let shift =
+      i64::from(new.dimensions().ascender_height()) - i64::from(old.dimensions().ascender_height());
+
+    for (label, column, row, width) in &new_tiles {
+      let Some(&(old_column, old_row, old_width)) = old_tiles.get(label) else {
+        continue;
+      };
+      let (x_start, y_start) = Sheet::tile_origin(new, *column, *row);
+      let width = Sheet::span_width(new, *width);
+
+      let mut cut = Vec::new();
+      for (x, y, pixel) in Sheet::drawn_pixels(&image, old, old_column, old_row, old_width) {
+        let shifted = u32::try_from(i64::from(y) + shift).unwrap_or(u32::MAX);
+        if x >= width || shifted >= height {
+          cut.push(format!("({x}, {y})"));
+          continue;
+        }
+        migrated.put_pixel(x_start + x, y_start + shifted, pixel);
+      }
+
+      if !cut.is_empty() {
+        eprintln!(
+          "Warning: {label} no longer fits its tile, the pixels at {} were cut off",
+          cut.join(", ")
+        );
+      }
+    }
+
+    let kept: HashSet<&String> = new_tiles.iter().map(|(label, ..)| label).collect();
+    let mut dropped: Vec<(&String, &(u32, u32, u32))> = old_tiles
+      .iter()
+      .filter(|(label, _)| !kept.contains(label))
+      .collect();
+    dropped.sort_by_key(|(_, (column, row, _))| (*row, *column));
+    for (label, &(column, row, width)) in dropped {
+      if !Sheet::drawn_pixels(&image, old, column, row, width).is_empty() {
+        eprintln!("Warning: {label} has no tile in the new sheet and was left out");
+      }
+    }
+
+    Ok(migrated)
+  }
+
+  // every pixel of a tile that isn't sheet background,
+  //   from the top left of the tile
+  fn drawn_pixels(
+    image: &RgbaImage,
+    config: &Config,
+    column: u32,
+    row: u32,
+    width: u32,
+  ) -> Vec<(u32, u32, Rgba<u8>)> {
+    let (x_start, y_start) = Sheet::tile_origin(config, column, row);
+    let width = Sheet::span_width(config, width);
+    let height = u32::from(config.dimensions().tile_height());
+    let palette = config.layout().palette();
+    let background = palette.background();
+    let ink = config.ink();
+
+    let mut pixels = Vec::new();
+    for y in 0..height {
+      for x in 0..width {
+        let Some(pixel) = image.get_pixel_checked(x_start + x, y_start + y) else {
+          continue;
+        };
+        let opaque = pixel[3] == u8::MAX;
+        let transparent = pixel[3] == 0;
+        let color = [pixel[0], pixel[1], pixel[2]];
+        // gutter paint spilt into a tile, unless the ink rule draws with it
+        let gutter = color == palette.gutter() && !ink.is_ink(pixel.0);
+        if transparent || opaque && (background.contains(&color) || gutter) {
+          continue;
+        }
+        pixels.push((x, y, *pixel));
+      }
+    }
+
+    pixels
+  }
+
+  // every tile with what's drawn in it, the same tile
+  //   whatever the layout, as column, row and width
+  fn labelled_tiles(config: &Config) -> Vec<(String, u32, u32, u32)> {
+    let columns = config.layout().columns();
+    let glyphs = config
+      .charset()
+      .chars()
+      .into_iter()
+      .map(UnicodeChar::Char)
+      .chain([UnicodeChar::NotDef])
+      .enumerate()
+      .map(|(i, character)| {
+        let i = u32::try_from(i).unwrap_or(u32::MAX);
+        (character.to_string(), i % columns, i / columns, 1)
+      });
+
+    let mut ordinals: HashMap<(char, &str), u16> = HashMap::new();
+    let extras = config
+      .ligatures()
+      .iter()
+      .map(|ligature| format!("ligature \"{}\"", ligature.sequence()))
+      .chain(config.alternates().iter().map(|alternate| {
+        let c = alternate.character();
+        let ordinal = ordinals.entry((c, alternate.feature())).or_default();
+        let label = match *ordinal {
+          0 => format!("alternate {c}.{}", alternate.feature()),
+          n => format!("alternate {c}.{}.{n}", alternate.feature()),
+        };
+        *ordinal += 1;
+        label
+      }))
+      .chain(config.marks().iter().map(|c| format!("mark {c}")))
+      .zip(Sheet::extra_tiles(config))
+      .map(|(label, (column, row, width))| (label, column, row, width));
+
+    glyphs.chain(extras).collect()
+  }
+
+  fn read_tile(
+    image: &RgbaImage,
+    config: &Config,
+    x_start: u32,
+    y_start: u32,
+    width: u32,
+  ) -> Result<Tile> {
+    let height = u32::from(config.dimensions().tile_height());
     let y_offset: i16 = config.dimensions().ascender_height().try_into()?;
+    let ink = config.ink();
+    let background = config.layout().palette().background();
 
-    let mut points = Vec::new();
-    for j in 0..ROWS {
-      for i in 0..COLUMNS {
-        let mut cluster = Vec::new();
-        let x_start = i * tile_width;
-        let y_start = j * tile_height;
-        for y in y_start..(y_start + tile_height) {
-          for x in x_start..(x_start + tile_width) {
-            let pixel = image.get_pixel(x, y);
-            if pixel.0 == colors::GLYPH_COLOR {
-              let px: i16 = (x - x_start).try_into()?;
-
-              let y: i16 = y.try_into()?;
-              let y_start: i16 = y_start.try_into()?;
-              let py = y_offset - (y - y_start) - 1;
-
-              let p = Point::new(px, py);
-              cluster.push(p);
-            }
+    let mut tile = Tile::default();
+    for y in y_start..(y_start + height) {
+      for x in x_start..(x_start + width) {
+        let Some(pixel) = image.get_pixel_checked(x, y) else {
+          continue;
+        };
+        let opaque = pixel[3] == u8::MAX;
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let anchor = opaque && color == colors::ANCHOR_COLOR;
+        let points = if opaque && background.contains(&color) {
+          continue;
+        } else if anchor {
+          &mut tile.anchors
+        } else if ink.is_ink(pixel.0) {
+          &mut tile.pixels
+        } else {
+          if ink.is_near(pixel.0) {
+            tile.near_ink.push((x - x_start, y - y_start));
           }
+          continue;
+        };
+
+        let px: i16 = (x - x_start).try_into()?;
+
+        let y: i16 = y.try_into()?;
+        let y_start: i16 = y_start.try_into()?;
+        let py = y_offset - (y - y_start) - 1;
+
+        let p = Point::new(px, py);
+        points.push(p);
+        if ink == Ink::Color && !anchor {
+          tile.layers.entry(pixel.0).or_default().push(p);
         }
-        points.push(cluster);
       }
     }
 
-    Ok(points)
+    Ok(tile)
+  }
+
+  fn report_near_ink(tiles: &[Tile]) {
+    for tile in tiles.iter().filter(|tile| !tile.near_ink.is_empty()) {
+      let pixels: Vec<String> = tile
+        .near_ink
+        .iter()
+        .map(|(x, y)| format!("({x}, {y})"))
+        .collect();
+      eprintln!(
+        "Warning: tile row {}, column {} has pixels that are almost ink at {}",
+        tile.row,
+        tile.column,
+        pixels.join(", ")
+      );
+    }
+  }
+
+  // width and height of a blank sheet for `config`
+  fn size(config: &Config) -> (u32, u32) {
+    let layout = config.layout();
+    let columns = layout.columns();
+    let glyph_rows = Sheet::glyphs_max(config).div_ceil(columns);
+    let rows = Sheet::extra_tiles(config)
+      .last()
+      .map_or(glyph_rows, |(_, row, _)| row + 1);
+
+    let (width, _) = Sheet::tile_origin(config, columns, 0);
+    let (_, height) = Sheet::tile_origin(config, 0, rows);
+    (
+      width - layout.gutter() + layout.padding(),
+      height - layout.gutter() + layout.padding(),
+    )
+  }
+
+  // a tile for every character in the charset and one for .notdef
+  #[allow(clippy::cast_possible_truncation)]
+  fn glyphs_max(config: &Config) -> u32 {
+    config.charset().chars().len() as u32 + 1
+  }
+
+  // top left pixel of the tile in `column` and `row`
+  fn tile_origin(config: &Config, column: u32, row: u32) -> (u32, u32) {
+    let layout = config.layout();
+    let tile_width = u32::from(config.dimensions().tile_width()) + layout.gutter();
+    let tile_height = u32::from(config.dimensions().tile_height()) + layout.gutter();
+    (
+      layout.padding() + column * tile_width,
+      layout.padding() + row * tile_height,
+    )
+  }
+
+  // a tile `width` columns wide covers the gutters between them
+  fn span_width(config: &Config, width: u32) -> u32 {
+    let tile_width = u32::from(config.dimensions().tile_width());
+    width * tile_width + (width - 1) * config.layout().gutter()
+  }
+
+  // ligatures, alternates and marks follow the glyph tiles,
+  //   wrapping to a new row when one doesn't fit
+  fn extra_tiles(config: &Config) -> Vec<(u32, u32, u32)> {
+    let widths = config
+      .ligatures()
+      .iter()
+      .map(|ligature| u32::from(ligature.width()))
+      .chain(config.alternates().iter().map(|_| 1))
+      .chain(config.marks().iter().map(|_| 1));
+
+    let columns = config.layout().columns();
+    let mut tiles = Vec::new();
+    let mut column = 0;
+    let mut row = Sheet::glyphs_max(config).div_ceil(columns);
+    for width in widths {
+      let width = width.min(columns);
+      if column + width > columns {
+        column = 0;
+        row += 1;
+      }
+      tiles.push((column, row, width));
+      column += width;
+    }
+
+    tiles
   }
 }
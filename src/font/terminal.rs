@@ -0,0 +1,568 @@
+use std::{collections::BTreeSet, num::NonZeroU16, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+use crate::font::{dimensions::Dimensions, point::Point};
+
+// left, up, right and down arm of every box drawing character:
+//   light, heavy, double or none
+#[rustfmt::skip]
+const BOX_ARMS: [&str; 128] = [
+  // U+2500
+  "l.l.", "h.h.", ".l.l", ".h.h", "l.l.", "h.h.", ".l.l", ".h.h",
+  // U+2508
+  "l.l.", "h.h.", ".l.l", ".h.h", "..ll", "..hl", "..lh", "..hh",
+  // U+2510
+  "l..l", "h..l", "l..h", "h..h", ".ll.", ".lh.", ".hl.", ".hh.",
+  // U+2518
+  "ll..", "hl..", "lh..", "hh..", ".lll", ".lhl", ".hll", ".llh",
+  // U+2520
+  ".hlh", ".hhl", ".lhh", ".hhh", "ll.l", "hl.l", "lh.l", "ll.h",
+  // U+2528
+  "lh.h", "hh.l", "hl.h", "hh.h", "l.ll", "h.ll", "l.hl", "h.hl",
+  // U+2530
+  "l.lh", "h.lh", "l.hh", "h.hh", "lll.", "hll.", "llh.", "hlh.",
+  // U+2538
+  "lhl.", "hhl.", "lhh.", "hhh.", "llll", "hlll", "llhl", "hlhl",
+  // U+2540
+  "lhll", "lllh", "lhlh", "hhll", "lhhl", "hllh", "llhh", "hhhl",
+  // U+2548
+  "hlhh", "hhlh", "lhhh", "hhhh", "l.l.", "h.h.", ".l.l", ".h.h",
+  // U+2550
+  "d.d.", ".d.d", "..dl", "..ld", "..dd", "d..l", "l..d", "d..d",
+  // U+2558
+  ".ld.", ".dl.", ".dd.", "dl..", "ld..", "dd..", ".ldl", ".dld",
+  // U+2560
+  ".ddd", "dl.l", "ld.d", "dd.d", "d.dl", "l.ld", "d.dd", "dld.",
+  // U+2568
+  "ldl.", "ddd.", "dldl", "ldld", "dddd", "..ll", "l..l", "ll..",
+  // U+2570
+  ".ll.", "....", "....", "....", "l...", ".l..", "..l.", "...l",
+  // U+2578
+  "h...", ".h..", "..h.", "...h", "l.h.", ".l.h", "h.l.", ".h.l",
+];
+
+const BOX_DRAWING: Range<u32> = 0x2500..0x2580;
+const BLOCK_ELEMENTS: Range<u32> = 0x2580..0x25A0;
+const BRAILLE: Range<u32> = 0x2800..0x2900;
+const LEGACY_SEXTANTS: Range<u32> = 0x1FB00..0x1FB3C;
+const LEGACY_EIGHTHS: Range<u32> = 0x1FB70..0x1FB8C;
+const POWERLINE: Range<u32> = 0xE0B0..0xE0C0;
+
+#[derive(Serialize, Deserialize)]
+pub struct Terminal {
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  packs: Vec<Pack>,
+  // pixels across a line, braille dot or thin separator
+  #[serde(default = "default_weight", skip_serializing_if = "is_default_weight")]
+  weight: NonZeroU16,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pack {
+  BoxDrawing,
+  BlockElements,
+  Braille,
+  // the sextants and the eighth blocks
+  LegacyComputing,
+  // the separators, solid, thin, round and slanted
+  Powerline,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Line {
+  None,
+  Light,
+  Heavy,
+  Double,
+}
+
+// the tile a glyph is generated on, top row first
+struct Canvas {
+  width: i32,
+  height: i32,
+  ascender: i32,
+  weight: i32,
+  pixels: BTreeSet<(i32, i32)>,
+}
+
+impl Terminal {
+  pub fn is_default(&self) -> bool {
+    self.packs.is_empty() && is_default_weight(&self.weight)
+  }
+
+  // the pixels of every character in the packs, ready to be traced
+  pub fn glyphs(&self, dimensions: &Dimensions) -> Vec<(char, Vec<Point>)> {
+    let mut chars: Vec<char> = self.packs.iter().flat_map(|pack| pack.chars()).collect();
+    chars.sort_unstable();
+    chars.dedup();
+
+    chars
+      .into_iter()
+      .map(|c| {
+        let mut canvas = Canvas::new(dimensions, self.weight.get());
+        canvas.draw(c);
+        (c, canvas.points())
+      })
+      .collect()
+  }
+}
+
+impl Default for Terminal {
+  fn default() -> Self {
+    Self {
+      packs: Vec::new(),
+      weight: default_weight(),
+    }
+  }
+}
+
+impl Pack {
+  fn chars(self) -> Vec<char> {
+    let ranges = match self {
+      Pack::BoxDrawing => vec![BOX_DRAWING],
+      Pack::BlockElements => vec![BLOCK_ELEMENTS],
+      Pack::Braille => vec![BRAILLE],
+      Pack::LegacyComputing => vec![LEGACY_SEXTANTS, LEGACY_EIGHTHS],
+      Pack::Powerline => vec![POWERLINE],
+    };
+    ranges
+      .into_iter()
+      .flatten()
+      .filter_map(char::from_u32)
+      .collect()
+  }
+}
+
+// characters drawn edge to edge so neighbours join up,
+//   whether generated or drawn, never kerned and always a tile wide
+pub fn fills_tile(c: char) -> bool {
+  [
+    BOX_DRAWING,
+    BLOCK_ELEMENTS,
+    BRAILLE,
+    LEGACY_SEXTANTS,
+    LEGACY_EIGHTHS,
+    POWERLINE,
+  ]
+  .iter()
+  .any(|range| range.contains(&u32::from(c)))
+}
+
+impl Line {
+  fn new(arm: u8) -> Self {
+    match arm {
+      b'l' => Line::Light,
+      b'h' => Line::Heavy,
+      b'd' => Line::Double,
+      _ => Line::None,
+    }
+  }
+
+  // the strokes of the line across `size` pixels, centred
+  #[allow(clippy::single_range_in_vec_init)]
+  fn bands(self, size: i32, weight: i32) -> Vec<Range<i32>> {
+    match self {
+      Line::None => Vec::new(),
+      Line::Light => {
+        let start = (size - weight) / 2;
+        vec![start..start + weight]
+      }
+      Line::Heavy => {
+        let start = (size - 2 * weight) / 2;
+        vec![start..start + 2 * weight]
+      }
+      Line::Double => {
+        let start = (size - 3 * weight) / 2;
+        vec![
+          start..start + weight,
+          start + 2 * weight..start + 3 * weight,
+        ]
+      }
+    }
+  }
+}
+
+#[allow(
+  clippy::cast_possible_truncation,
+  clippy::cast_possible_wrap,
+  clippy::cast_sign_loss
+)]
+impl Canvas {
+  fn new(dimensions: &Dimensions, weight: u16) -> Self {
+    Self {
+      width: i32::from(dimensions.tile_width()),
+      height: i32::from(dimensions.tile_height()),
+      ascender: i32::from(dimensions.ascender_height()),
+      weight: i32::from(weight),
+      pixels: BTreeSet::new(),
+    }
+  }
+
+  fn fill(&mut self, x: Range<i32>, y: Range<i32>) {
+    for y in y.start.max(0)..y.end.min(self.height) {
+      for x in x.start.max(0)..x.end.min(self.width) {
+        self.pixels.insert((x, y));
+      }
+    }
+  }
+
+  fn points(self) -> Vec<Point> {
+    self
+      .pixels
+      .into_iter()
+      .map(|(x, y)| Point::new(x as i16, (self.ascender - y - 1) as i16))
+      .collect()
+  }
+
+  fn draw(&mut self, c: char) {
+    let code = u32::from(c);
+    if BOX_DRAWING.contains(&code) {
+      self.box_drawing(c);
+    } else if BLOCK_ELEMENTS.contains(&code) {
+      self.block_element(c);
+    } else if BRAILLE.contains(&code) {
+      self.braille((code - BRAILLE.start) as u8);
+    } else if LEGACY_SEXTANTS.contains(&code) {
+      self.sextant(code - LEGACY_SEXTANTS.start);
+    } else if LEGACY_EIGHTHS.contains(&code) {
+      self.legacy_eighths(c);
+    } else if POWERLINE.contains(&code) {
+      self.powerline(c);
+    }
+  }
+
+  // the boundary k eighths of the way across `size` pixels
+  fn eighth(size: i32, k: i32) -> i32 {
+    (size * k + 4) / 8
+  }
+
+  fn box_drawing(&mut self, c: char) {
+    let arms = BOX_ARMS[(u32::from(c) - BOX_DRAWING.start) as usize].as_bytes();
+    let [left, up, right, down] = [0, 1, 2, 3].map(|i| Line::new(arms[i]));
+    let dashes = match c {
+      '\u{2504}'..='\u{2507}' => 3,
+      '\u{2508}'..='\u{250B}' => 4,
+      '\u{254C}'..='\u{254F}' => 2,
+      _ => 1,
+    };
+    let arc = ('\u{256D}'..='\u{2570}').contains(&c);
+
+    match c {
+      '\u{2571}' => self.diagonal(false),
+      '\u{2572}' => self.diagonal(true),
+      '\u{2573}' => {
+        self.diagonal(false);
+        self.diagonal(true);
+      }
+      _ if dashes > 1 => {
+        self.dashes(left, dashes, false);
+        self.dashes(up, dashes, true);
+      }
+      _ => {
+        self.arms([left, up, right, down], arc, false);
+        self.arms([up, left, down, right], arc, true);
+      }
+    }
+  }
+
+  // fills along one axis, the other one when `transposed`
+  fn fill_along(&mut self, along: Range<i32>, across: Range<i32>, transposed: bool) {
+    if transposed {
+      self.fill(across, along);
+    } else {
+      self.fill(along, across);
+    }
+  }
+
+  // the arms `before` and `after` the centre on one axis,
+  //   each stroke joining the stroke of the arm on its side,
+  //   or running on through the centre when there is none
+  fn arms(&mut self, [before, side_a, after, side_b]: [Line; 4], arc: bool, transposed: bool) {
+    let (length, across) = if transposed {
+      (self.height, self.width)
+    } else {
+      (self.width, self.height)
+    };
+    let weight = self.weight;
+
+    let centre = Line::Light.bands(length, weight)[0].clone();
+    let sides = [side_a.bands(length, weight), side_b.bands(length, weight)];
+    let crossing: Vec<&Range<i32>> = sides.iter().flatten().collect();
+    let first = crossing
+      .iter()
+      .map(|b| b.start)
+      .min()
+      .unwrap_or(centre.start);
+    let last = crossing.iter().map(|b| b.end).max().unwrap_or(centre.end);
+
+    for (arm, opposite, is_after) in [(before, after, false), (after, before, true)] {
+      let strokes = arm.bands(across, weight);
+      for (i, stroke) in strokes.iter().enumerate() {
+        let partners = [
+          (i == 0).then_some(&sides[0]),
+          (i == strokes.len() - 1).then_some(&sides[1]),
+        ];
+        let mut start = i32::MAX;
+        let mut end = i32::MIN;
+        for side in partners.into_iter().flatten() {
+          // the partner's stroke nearest this arm
+          let near = if is_after { side.last() } else { side.first() };
+          start = start.min(near.map_or(first, |b| b.start));
+          end = end.max(near.map_or(last, |b| b.end));
+        }
+        if arm != Line::Double && opposite != Line::None {
+          start = start.min(centre.start);
+          end = end.max(centre.end);
+        }
+        if arc {
+          start += weight;
+          end -= weight;
+        }
+
+        let along = if is_after { start..length } else { 0..end };
+        self.fill_along(along, stroke.clone(), transposed);
+      }
+    }
+  }
+
+  // a line across the whole tile broken into `count` dashes
+  fn dashes(&mut self, line: Line, count: i32, transposed: bool) {
+    let (length, across) = if transposed {
+      (self.height, self.width)
+    } else {
+      (self.width, self.height)
+    };
+
+    for stroke in line.bands(across, self.weight) {
+      for i in 0..count {
+        let start = i * length / count;
+        let end = (i + 1) * length / count;
+        let gap = (end - start + 2) / 3;
+        let dash = start + gap / 2..end - (gap - gap / 2);
+        self.fill_along(dash, stroke.clone(), transposed);
+      }
+    }
+  }
+
+  // corner to corner, from the top left when `falling`
+  fn diagonal(&mut self, falling: bool) {
+    let offset = (self.weight - 1) / 2;
+    for y in 0..self.height {
+      let x = if self.height > 1 {
+        (2 * y * (self.width - 1) + self.height - 1) / (2 * (self.height - 1))
+      } else {
+        0
+      };
+      let x = if falling { x } else { self.width - 1 - x };
+      self.fill(x - offset..x - offset + self.weight, y..y + 1);
+    }
+  }
+
+  fn block_element(&mut self, c: char) {
+    let (width, height) = (self.width, self.height);
+    let column = |k| Canvas::eighth(width, k);
+    let row = |k| Canvas::eighth(height, k);
+
+    match c {
+      '\u{2580}' => self.fill(0..width, 0..row(4)),
+      '\u{2581}'..='\u{2588}' => {
+        let k = (u32::from(c) - 0x2580) as i32;
+        self.fill(0..width, row(8 - k)..height);
+      }
+      '\u{2589}'..='\u{258F}' => {
+        let k = 0x2590 - u32::from(c) as i32;
+        self.fill(0..column(k), 0..height);
+      }
+      '\u{2590}' => self.fill(column(4)..width, 0..height),
+      '\u{2591}'..='\u{2593}' => {
+        for y in 0..height {
+          for x in 0..width {
+            let shaded = match c {
+              '\u{2591}' => x % 2 == 0 && y % 2 == 0,
+              '\u{2592}' => (x + y) % 2 == 0,
+              _ => x % 2 == 0 || y % 2 == 0,
+            };
+            if shaded {
+              self.fill(x..x + 1, y..y + 1);
+            }
+          }
+        }
+      }
+      '\u{2594}' => self.fill(0..width, 0..row(1)),
+      '\u{2595}' => self.fill(column(7)..width, 0..height),
+      _ => {
+        // upper left, upper right, lower left and lower right
+        let quadrants: u8 = match c {
+          '\u{2596}' => 0b0100,
+          '\u{2597}' => 0b1000,
+          '\u{2598}' => 0b0001,
+          '\u{2599}' => 0b1101,
+          '\u{259A}' => 0b1001,
+          '\u{259B}' => 0b0111,
+          '\u{259C}' => 0b1011,
+          '\u{259D}' => 0b0010,
+          '\u{259E}' => 0b0110,
+          _ => 0b1110,
+        };
+        self.cells(
+          quadrants.into(),
+          &[0, column(4), width],
+          &[0, row(4), height],
+        );
+      }
+    }
+  }
+
+  // fills the cells of a grid whose bits are set, row by row
+  fn cells(&mut self, bits: u32, columns: &[i32], rows: &[i32]) {
+    let count = columns.len() - 1;
+    for (i, (x, y)) in (0..rows.len() - 1)
+      .flat_map(|y| (0..count).map(move |x| (x, y)))
+      .enumerate()
+    {
+      if bits & (1 << i) != 0 {
+        self.fill(columns[x]..columns[x + 1], rows[y]..rows[y + 1]);
+      }
+    }
+  }
+
+  fn braille(&mut self, dots: u8) {
+    // dot numbers 1 to 8 as column and row
+    const DOTS: [(i32, i32); 8] = [
+      (0, 0),
+      (0, 1),
+      (0, 2),
+      (1, 0),
+      (1, 1),
+      (1, 2),
+      (0, 3),
+      (1, 3),
+    ];
+
+    let columns = [0, (self.width + 1) / 2, self.width];
+    let rows = [0, 1, 2, 3, 4].map(|k| (self.height * k + 2) / 4);
+    for (bit, (column, row)) in DOTS.into_iter().enumerate() {
+      if dots & (1 << bit) == 0 {
+        continue;
+      }
+      let (column, row) = (column as usize, row as usize);
+      let x = columns[column] + (columns[column + 1] - columns[column] - self.weight) / 2;
+      let y = rows[row] + (rows[row + 1] - rows[row] - self.weight) / 2;
+      self.fill(x..x + self.weight, y..y + self.weight);
+    }
+  }
+
+  // two columns by three rows, skipping the ones that are
+  //   already block elements
+  fn sextant(&mut self, index: u32) {
+    const LEFT_HALF: u32 = 0b01_0101;
+    const RIGHT_HALF: u32 = 0b10_1010;
+
+    let mut bits = index + 1;
+    if bits >= LEFT_HALF {
+      bits += 1;
+    }
+    if bits >= RIGHT_HALF {
+      bits += 1;
+    }
+
+    let columns = [0, (self.width + 1) / 2, self.width];
+    let rows = [0, 1, 2, 3].map(|k| (self.height * k + 1) / 3);
+    self.cells(bits, &columns, &rows);
+  }
+
+  fn legacy_eighths(&mut self, c: char) {
+    let (width, height) = (self.width, self.height);
+    let column = |k| Canvas::eighth(width, k);
+    let row = |k| Canvas::eighth(height, k);
+
+    match c {
+      '\u{1FB70}'..='\u{1FB75}' => {
+        let k = (u32::from(c) - 0x1FB6F) as i32;
+        self.fill(column(k)..column(k + 1), 0..height);
+      }
+      '\u{1FB76}'..='\u{1FB7B}' => {
+        let k = (u32::from(c) - 0x1FB75) as i32;
+        self.fill(0..width, row(k)..row(k + 1));
+      }
+      '\u{1FB7C}'..='\u{1FB80}' => {
+        let (left, right, upper, lower) = match c {
+          '\u{1FB7C}' => (true, false, false, true),
+          '\u{1FB7D}' => (true, false, true, false),
+          '\u{1FB7E}' => (false, true, true, false),
+          '\u{1FB7F}' => (false, true, false, true),
+          _ => (false, false, true, true),
+        };
+        if left {
+          self.fill(0..column(1), 0..height);
+        }
+        if right {
+          self.fill(column(7)..width, 0..height);
+        }
+        if upper {
+          self.fill(0..width, 0..row(1));
+        }
+        if lower {
+          self.fill(0..width, row(7)..height);
+        }
+      }
+      '\u{1FB81}' => {
+        for k in [0, 2, 4, 7] {
+          self.fill(0..width, row(k)..row(k + 1));
+        }
+      }
+      '\u{1FB82}'..='\u{1FB86}' => {
+        let k = [2, 3, 5, 6, 7][(u32::from(c) - 0x1FB82) as usize];
+        self.fill(0..width, 0..row(k));
+      }
+      _ => {
+        let k = [2, 3, 5, 6, 7][(u32::from(c) - 0x1FB87) as usize];
+        self.fill(column(8 - k)..width, 0..height);
+      }
+    }
+  }
+
+  fn powerline(&mut self, c: char) {
+    let (width, height) = (self.width, self.height);
+    let weight = self.weight;
+
+    for y in 0..height {
+      let extent = match c {
+        // pointing at the middle row
+        '\u{E0B0}'..='\u{E0B3}' => {
+          let distance = (2 * y + 1 - height).abs();
+          (width * (height - distance) + height / 2) / height
+        }
+        // half an ellipse around the middle row
+        '\u{E0B4}'..='\u{E0B7}' => {
+          let dy = f64::from(2 * y + 1 - height) / f64::from(height);
+          (f64::from(width) * (1.0 - dy * dy).sqrt()).round() as i32
+        }
+        // widening downwards, then upwards
+        '\u{E0B8}'..='\u{E0BB}' => ((y + 1) * width + height / 2) / height,
+        _ => ((height - y) * width + height / 2) / height,
+      };
+
+      let filled = match u32::from(c) % 4 {
+        0 => 0..extent,
+        1 => extent - weight..extent,
+        2 => width - extent..width,
+        _ => width - extent..width - extent + weight,
+      };
+      if !filled.is_empty() && extent > 0 {
+        self.fill(filled, y..y + 1);
+      }
+    }
+  }
+}
+
+fn default_weight() -> NonZeroU16 {
+  NonZeroU16::MIN
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_weight(weight: &NonZeroU16) -> bool {
+  *weight == default_weight()
+}
@@ -1,12 +1,30 @@
 use std::{cmp::Ordering, fmt::Display};
 
+use read_fonts::types::Tag;
+use unicode_normalization::char::{canonical_combining_class, is_combining_mark};
+
+use crate::font::terminal;
+
 const SPACE: char = '\u{0020}';
 const NBSP: char = '\u{00A0}';
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+const COMBINING_ATTACHED_BELOW: u8 = 202;
+const COMBINING_BELOW: u8 = 220;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UnicodeChar {
   NotDef,
   Char(char),
+  Alternate(char, Tag, u16),
+  Ligature(u16),
+  // one colour of a colour glyph
+  Layer(u16),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MarkClass {
+  Top,
+  Bottom,
 }
 
 impl UnicodeChar {
@@ -14,7 +32,39 @@ impl UnicodeChar {
   pub const UNICODE_NBSP: UnicodeChar = UnicodeChar::Char(NBSP);
 
   pub fn should_kern(self) -> bool {
-    self != UnicodeChar::NotDef && !self.is_space()
+    matches!(self, UnicodeChar::Char(_))
+      && self.mark_class().is_none()
+      && !self.is_space()
+      && !self.fills_tile()
+  }
+
+  pub fn fills_tile(self) -> bool {
+    matches!(self, UnicodeChar::Char(c) if terminal::fills_tile(c))
+  }
+
+  // .notdef first, then encoded glyphs, then unencoded ones
+  fn rank(self) -> u8 {
+    match self {
+      UnicodeChar::NotDef => 0,
+      UnicodeChar::Char(_) => 1,
+      UnicodeChar::Alternate(..) => 2,
+      UnicodeChar::Ligature(_) => 3,
+      UnicodeChar::Layer(_) => 4,
+    }
+  }
+
+  pub fn mark_class(self) -> Option<MarkClass> {
+    let UnicodeChar::Char(c) = self else {
+      return None;
+    };
+    if !is_combining_mark(c) {
+      return None;
+    }
+
+    match canonical_combining_class(c) {
+      COMBINING_ATTACHED_BELOW | COMBINING_BELOW => Some(MarkClass::Bottom),
+      _ => Some(MarkClass::Top),
+    }
   }
 
   pub fn is_space(self) -> bool {
@@ -29,6 +79,9 @@ impl TryFrom<UnicodeChar> for char {
     match value {
       UnicodeChar::NotDef => Err(".notdef has no unicode equivalent"),
       UnicodeChar::Char(c) => Ok(c),
+      UnicodeChar::Alternate(..) => Err("alternates have no unicode equivalent"),
+      UnicodeChar::Ligature(_) => Err("ligatures have no unicode equivalent"),
+      UnicodeChar::Layer(_) => Err("colour layers have no unicode equivalent"),
     }
   }
 }
@@ -38,6 +91,10 @@ impl Display for UnicodeChar {
     match self {
       UnicodeChar::NotDef => write!(f, ".notdef"),
       UnicodeChar::Char(c) => write!(f, "{c}"),
+      UnicodeChar::Alternate(c, tag, 0) => write!(f, "{c}.{tag}"),
+      UnicodeChar::Alternate(c, tag, n) => write!(f, "{c}.{tag}.{n}"),
+      UnicodeChar::Ligature(i) => write!(f, "ligature.{i}"),
+      UnicodeChar::Layer(i) => write!(f, "layer.{i}"),
     }
   }
 }
@@ -51,10 +108,13 @@ impl PartialOrd for UnicodeChar {
 impl Ord for UnicodeChar {
   fn cmp(&self, other: &Self) -> Ordering {
     match (self, other) {
-      (UnicodeChar::NotDef, UnicodeChar::NotDef) => Ordering::Equal,
-      (UnicodeChar::NotDef, UnicodeChar::Char(_)) => Ordering::Less,
-      (UnicodeChar::Char(_), UnicodeChar::NotDef) => Ordering::Greater,
       (UnicodeChar::Char(a), UnicodeChar::Char(b)) => a.cmp(b),
+      (UnicodeChar::Alternate(a, a_tag, a_n), UnicodeChar::Alternate(b, b_tag, b_n)) => {
+        (a_tag, a, a_n).cmp(&(b_tag, b, b_n))
+      }
+      (UnicodeChar::Ligature(a), UnicodeChar::Ligature(b))
+      | (UnicodeChar::Layer(a), UnicodeChar::Layer(b)) => a.cmp(b),
+      _ => self.rank().cmp(&other.rank()),
     }
   }
 }